@@ -2,7 +2,12 @@
 
 use proc_macro::TokenStream;
 
+use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use syn::parse::Parse;
+use syn::parse::ParseStream;
 use syn::Error;
 use syn::ItemFn;
 
@@ -71,18 +76,32 @@ pub fn make_read_env_var(input: TokenStream) -> TokenStream {
 ///     // async code
 /// }
 /// ```
+/// With the `log` feature of libtock, `#[libtock::main(log = "info")]` registers the console
+/// logger with the given maximum level before `main` is run.
 #[proc_macro_attribute]
-pub fn main(_: TokenStream, input: TokenStream) -> TokenStream {
-    generate_main_wrapped(input.into()).into()
+pub fn main(attr: TokenStream, input: TokenStream) -> TokenStream {
+    generate_main_wrapped(attr.into(), input.into()).into()
 }
 
-fn generate_main_wrapped(input: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
-    try_generate_main_wrapped(input).unwrap_or_else(|err| err.to_compile_error())
+fn generate_main_wrapped(
+    attr: proc_macro2::TokenStream,
+    input: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    try_generate_main_wrapped(attr, input).unwrap_or_else(|err| err.to_compile_error())
 }
 
 fn try_generate_main_wrapped(
+    attr: proc_macro2::TokenStream,
     input: proc_macro2::TokenStream,
 ) -> Result<proc_macro2::TokenStream, Error> {
+    let init_logger = match parse_log_level(attr)? {
+        Some(level) => quote!(
+            let _ = ::libtock::logger::init(::libtock::logger::ConsoleLogger::new(
+                ::libtock::logger::LevelFilter::#level,
+            ));
+        ),
+        None => quote!(),
+    };
     let ast = syn::parse2::<ItemFn>(input)?;
     let block = ast.block;
     let output = &ast.sig.output;
@@ -95,12 +114,205 @@ fn try_generate_main_wrapped(
                 }
                 MAIN_INVOKED = true;
             }
+            #init_logger
             let _block = async #block;
             unsafe { ::libtock::executor::block_on(_block) }
         }
     ))
 }
 
+/// Parses the optional `log = "<level>"` argument and returns the name of the `LevelFilter`
+/// variant.
+fn parse_log_level(attr: proc_macro2::TokenStream) -> Result<Option<syn::Ident>, Error> {
+    if attr.is_empty() {
+        return Ok(None);
+    }
+    let meta = syn::parse2::<syn::MetaNameValue>(attr)?;
+    if !meta.path.is_ident("log") {
+        return Err(Error::new_spanned(
+            meta.path,
+            "expected `log = \"<level>\"`",
+        ));
+    }
+    let level = match &meta.lit {
+        syn::Lit::Str(level) => level,
+        other => return Err(Error::new_spanned(other, "expected a string literal")),
+    };
+    let variant = match level.value().as_str() {
+        "off" => "Off",
+        "error" => "Error",
+        "warn" => "Warn",
+        "info" => "Info",
+        "debug" => "Debug",
+        "trace" => "Trace",
+        _ => {
+            return Err(Error::new_spanned(
+                level,
+                "expected one of off, error, warn, info, debug and trace",
+            ))
+        }
+    };
+    Ok(Some(syn::Ident::new(variant, level.span())))
+}
+
+/// Procedural macros behind `libtock::binlog::{error, warn, info, debug, trace}`. See the `binlog`
+/// module of libtock for the format string syntax.
+#[proc_macro]
+pub fn binlog_error(input: TokenStream) -> TokenStream {
+    generate_binlog_wrapped("error", input.into()).into()
+}
+
+#[proc_macro]
+pub fn binlog_warn(input: TokenStream) -> TokenStream {
+    generate_binlog_wrapped("warn", input.into()).into()
+}
+
+#[proc_macro]
+pub fn binlog_info(input: TokenStream) -> TokenStream {
+    generate_binlog_wrapped("info", input.into()).into()
+}
+
+#[proc_macro]
+pub fn binlog_debug(input: TokenStream) -> TokenStream {
+    generate_binlog_wrapped("debug", input.into()).into()
+}
+
+#[proc_macro]
+pub fn binlog_trace(input: TokenStream) -> TokenStream {
+    generate_binlog_wrapped("trace", input.into()).into()
+}
+
+/// Number of binary log statements generated so far for the crate being compiled. Makes the
+/// symbol names of statements with the same format string unique.
+static BINLOG_STATEMENTS: AtomicUsize = AtomicUsize::new(0);
+
+fn generate_binlog_wrapped(level: &str, input: TokenStream2) -> TokenStream2 {
+    let crate_name = std::env::var("CARGO_CRATE_NAME").unwrap_or_default();
+    let index = BINLOG_STATEMENTS.fetch_add(1, Ordering::Relaxed);
+    generate_binlog(level, &crate_name, index, input).unwrap_or_else(|err| err.to_compile_error())
+}
+
+struct BinlogInput {
+    format: syn::LitStr,
+    args: Vec<syn::Expr>,
+}
+
+impl Parse for BinlogInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let format = input.parse()?;
+        let mut args = Vec::new();
+        while !input.is_empty() {
+            input.parse::<syn::Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            args.push(input.parse()?);
+        }
+        Ok(BinlogInput { format, args })
+    }
+}
+
+/// The statement is represented by a one-byte static in the `.binlog` section whose symbol name
+/// `binlog;<level>;<crate>;<index>;<format>` carries the format string. The address of the static
+/// is sent instead of the text.
+fn generate_binlog(
+    level: &str,
+    crate_name: &str,
+    index: usize,
+    input: TokenStream2,
+) -> Result<TokenStream2, Error> {
+    let input = syn::parse2::<BinlogInput>(input)?;
+    let format = input.format.value();
+    let encode_fns = parse_binlog_format(&format)
+        .map_err(|message| Error::new_spanned(&input.format, message))?;
+    if encode_fns.len() != input.args.len() {
+        return Err(Error::new_spanned(
+            &input.format,
+            format!(
+                "format string has {} placeholders but {} arguments were given",
+                encode_fns.len(),
+                input.args.len()
+            ),
+        ));
+    }
+    let symbol = format!("binlog;{};{};{};{}", level, crate_name, index, format);
+    let encode_fns = encode_fns
+        .iter()
+        .map(|encode_fn| syn::Ident::new(encode_fn, proc_macro2::Span::call_site()));
+    let args = &input.args;
+    Ok(quote!({
+        #[link_section = ".binlog"]
+        #[export_name = #symbol]
+        static __BINLOG_SITE: u8 = 0;
+        let mut __binlog_encoder =
+            ::libtock::binlog::Encoder::new(&__BINLOG_SITE as *const u8 as usize);
+        #(__binlog_encoder.#encode_fns(#args);)*
+        __binlog_encoder.finish();
+    }))
+}
+
+/// Returns the names of the `Encoder` functions for the placeholders `{=<type>}` or
+/// `{=<type>:<hint>}` of the format string.
+fn parse_binlog_format(format: &str) -> Result<Vec<&'static str>, String> {
+    if format.contains('\0') {
+        return Err("format string must not contain NUL characters".into());
+    }
+    let mut encode_fns = Vec::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '{' => {
+                let placeholder: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                encode_fns.push(parse_binlog_placeholder(&placeholder)?);
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+            }
+            '}' => return Err("unmatched `}` in format string, use `}}` to escape it".into()),
+            _ => {}
+        }
+    }
+    Ok(encode_fns)
+}
+
+fn parse_binlog_placeholder(placeholder: &str) -> Result<&'static str, String> {
+    let error = || {
+        format!(
+            "invalid placeholder `{{{}}}`, expected `{{=<type>}}` or `{{=<type>:<x|b>}}` with \
+             type u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, bool, str or [u8]",
+            placeholder
+        )
+    };
+    if placeholder.is_empty() || placeholder.as_bytes()[0] != b'=' {
+        return Err(error());
+    }
+    let mut parts = placeholder[1..].splitn(2, ':');
+    let ty = parts.next().unwrap_or_default();
+    match parts.next() {
+        None | Some("x") | Some("b") => {}
+        Some(_) => return Err(error()),
+    }
+    Ok(match ty {
+        "u8" => "encode_u8",
+        "u16" => "encode_u16",
+        "u32" => "encode_u32",
+        "u64" => "encode_u64",
+        "usize" => "encode_usize",
+        "i8" => "encode_i8",
+        "i16" => "encode_i16",
+        "i32" => "encode_i32",
+        "i64" => "encode_i64",
+        "isize" => "encode_isize",
+        "bool" => "encode_bool",
+        "str" => "encode_str",
+        "[u8]" => "encode_bytes",
+        _ => return Err(error()),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,7 +323,8 @@ mod tests {
                 method_call().await;
             }
         };
-        let actual: ItemFn = syn::parse2::<ItemFn>(generate_main_wrapped(method_def)).unwrap();
+        let actual: ItemFn =
+            syn::parse2::<ItemFn>(generate_main_wrapped(quote!(), method_def)).unwrap();
         let expected: ItemFn = syn::parse2::<ItemFn>(quote!(
             fn main() -> ::libtock::result::TockResult<()> {
                 static mut MAIN_INVOKED: bool = false;
@@ -130,4 +343,82 @@ mod tests {
         .unwrap();
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn initializes_logger_if_requested() {
+        let method_def: proc_macro2::TokenStream = quote! {
+            async fn main() {}
+        };
+        let actual: ItemFn =
+            syn::parse2::<ItemFn>(generate_main_wrapped(quote!(log = "debug"), method_def))
+                .unwrap();
+        let expected: ItemFn = syn::parse2::<ItemFn>(quote!(
+            fn main() {
+                static mut MAIN_INVOKED: bool = false;
+                unsafe {
+                    if MAIN_INVOKED {
+                        panic!("Main called recursively; this is unsafe with #[libtock::main]");
+                    }
+                    MAIN_INVOKED = true;
+                }
+                let _ = ::libtock::logger::init(::libtock::logger::ConsoleLogger::new(
+                    ::libtock::logger::LevelFilter::Debug,
+                ));
+                let _block = async {};
+                unsafe { ::libtock::executor::block_on(_block) }
+            }
+        ))
+        .unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn rejects_unknown_log_levels() {
+        assert!(parse_log_level(quote!(log = "verbose")).is_err());
+        assert!(parse_log_level(quote!(level = "info")).is_err());
+    }
+
+    #[test]
+    fn interns_binlog_format_strings() {
+        let actual: syn::Block = syn::parse2(
+            generate_binlog("info", "app", 3, quote!("{=u8} of {=str}", count, name)).unwrap(),
+        )
+        .unwrap();
+        let expected: syn::Block = syn::parse2(quote!({
+            #[link_section = ".binlog"]
+            #[export_name = "binlog;info;app;3;{=u8} of {=str}"]
+            static __BINLOG_SITE: u8 = 0;
+            let mut __binlog_encoder =
+                ::libtock::binlog::Encoder::new(&__BINLOG_SITE as *const u8 as usize);
+            __binlog_encoder.encode_u8(count);
+            __binlog_encoder.encode_str(name);
+            __binlog_encoder.finish();
+        }))
+        .unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parses_binlog_placeholders() {
+        assert_eq!(
+            parse_binlog_format("{{}} {=u32:x} {=[u8]} {=isize} {=bool}"),
+            Ok(vec![
+                "encode_u32",
+                "encode_bytes",
+                "encode_isize",
+                "encode_bool"
+            ])
+        );
+        assert!(parse_binlog_format("{}").is_err());
+        assert!(parse_binlog_format("{=f32}").is_err());
+        assert!(parse_binlog_format("{=u8:?}").is_err());
+        assert!(parse_binlog_format("}").is_err());
+    }
+
+    #[test]
+    fn rejects_binlog_argument_count_mismatch() {
+        assert!(generate_binlog("warn", "app", 0, quote!("{=u8}"),).is_err());
+        assert!(generate_binlog("warn", "app", 0, quote!("", 1)).is_err());
+        assert!(generate_binlog("warn", "app", 0, quote!("{=u8}", 1,)).is_ok());
+    }
 }
@@ -34,7 +34,5 @@ unsafe fn alloc_error_handler(_: Layout) -> ! {
     // Signal a panic using the LowLevelDebug capsule (if available).
     let _ = syscalls::command1_insecure(8, 1, 0x01);
 
-    loop {
-        syscalls::raw::yieldk();
-    }
+    syscalls::exit(1)
 }
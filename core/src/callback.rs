@@ -1,6 +1,9 @@
 use crate::syscalls;
+#[cfg(not(any(target_arch = "arm", target_arch = "riscv32")))]
+use core::cell::RefCell;
 use core::marker::PhantomData;
 use core::ptr;
+use core::task::Waker;
 
 pub trait Consumer<T> {
     fn consume(data: &mut T, arg1: usize, arg2: usize, arg3: usize);
@@ -60,5 +63,167 @@ impl<'a> Drop for CallbackSubscription<'a> {
         unsafe {
             syscalls::raw::subscribe(self.driver_number, self.subscribe_number, ptr::null(), 0);
         }
+        unbind_upcall(self.driver_number, self.subscribe_number);
+    }
+}
+
+/// Number of wakers which can wait for upcalls at the same time. If more wakers are registered,
+/// the surplus ones are woken immediately, i.e. their futures are polled again after the next
+/// yield.
+pub const MAX_UPCALL_WAKERS: usize = 16;
+
+/// Key of wakers which are woken by any upcall.
+const ANY_UPCALL: (usize, usize) = (usize::MAX, usize::MAX);
+
+#[derive(Default)]
+struct UpcallWakers {
+    wakers: [Option<((usize, usize), Waker)>; MAX_UPCALL_WAKERS],
+    // Maps the userdata passed to the kernel to the subscription it was passed with. This is how
+    // upcalls are attributed to driver and subscribe numbers. Subscriptions may share their
+    // userdata, e.g. zero-sized closures, in which case their upcalls cannot be attributed.
+    bindings: [Option<((usize, usize), usize)>; MAX_UPCALL_WAKERS],
+}
+
+impl UpcallWakers {
+    fn register(&mut self, key: (usize, usize), waker: &Waker) -> bool {
+        let already_registered = self.wakers.iter().any(|entry| match entry {
+            Some((entry_key, entry_waker)) => *entry_key == key && entry_waker.will_wake(waker),
+            None => false,
+        });
+        if already_registered {
+            return true;
+        }
+        match self.wakers.iter_mut().find(|entry| entry.is_none()) {
+            Some(entry) => {
+                *entry = Some((key, waker.clone()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn take(&mut self, mut matches: impl FnMut((usize, usize)) -> bool) -> Option<Waker> {
+        self.wakers
+            .iter_mut()
+            .find(|entry| match entry {
+                Some((key, _)) => matches(*key),
+                None => false,
+            })
+            .and_then(Option::take)
+            .map(|(_, waker)| waker)
+    }
+
+    fn bound_key(&self, userdata: usize) -> Option<(usize, usize)> {
+        let mut bound_keys = self.bindings.iter().filter_map(|binding| match binding {
+            Some((key, bound_userdata)) if *bound_userdata == userdata => Some(*key),
+            _ => None,
+        });
+        let bound_key = bound_keys.next()?;
+        match bound_keys.next() {
+            None => Some(bound_key),
+            Some(_) => None,
+        }
+    }
+}
+
+#[cfg(not(any(target_arch = "arm", target_arch = "riscv32")))]
+fn with_upcall_wakers<R, F: FnOnce(&mut UpcallWakers) -> R>(f: F) -> R {
+    thread_local!(static UPCALL_WAKERS: RefCell<UpcallWakers> = RefCell::new(UpcallWakers::default()));
+    UPCALL_WAKERS.with(|upcall_wakers| f(&mut upcall_wakers.borrow_mut()))
+}
+
+#[cfg(any(target_arch = "arm", target_arch = "riscv32"))]
+fn with_upcall_wakers<R, F: FnOnce(&mut UpcallWakers) -> R>(f: F) -> R {
+    static mut UPCALL_WAKERS: Option<UpcallWakers> = None;
+    // Apps are single-threaded and wakers are never woken while the registry is borrowed.
+    f(unsafe { UPCALL_WAKERS.get_or_insert_with(UpcallWakers::default) })
+}
+
+/// Wake the waker once an upcall for the given subscription has been delivered. Only upcalls
+/// of subscriptions created by [syscalls::subscribe()] are attributed to their subscription.
+pub fn register_upcall_waker(driver_number: usize, subscribe_number: usize, waker: &Waker) {
+    if !with_upcall_wakers(|wakers| wakers.register((driver_number, subscribe_number), waker)) {
+        waker.wake_by_ref();
+    }
+}
+
+/// Wake the waker once any upcall has been delivered. Executors have to call
+/// [wake_any_upcall_wakers()] after yielding.
+pub fn register_any_upcall_waker(waker: &Waker) {
+    if !with_upcall_wakers(|wakers| wakers.register(ANY_UPCALL, waker)) {
+        waker.wake_by_ref();
+    }
+}
+
+/// Wakes the wakers registered for the given subscription without an upcall, e.g. because the
+/// state they are waiting for has been changed by the app itself.
+pub fn wake_upcall_wakers_of(driver_number: usize, subscribe_number: usize) {
+    wake_matching(|key| key == (driver_number, subscribe_number));
+}
+
+/// Wakes the wakers registered by [register_any_upcall_waker()]. To be called by executors
+/// whenever a yield has delivered an upcall.
+pub fn wake_any_upcall_wakers() {
+    wake_matching(|key| key == ANY_UPCALL);
+}
+
+/// Removes all registrations of the waker, e.g. because the task it belongs to has finished.
+pub fn unregister_waker(waker: &Waker) {
+    with_upcall_wakers(|wakers| {
+        for entry in wakers.wakers.iter_mut() {
+            if let Some((_, registered)) = entry {
+                if registered.will_wake(waker) {
+                    *entry = None;
+                }
+            }
+        }
+    });
+}
+
+pub(crate) fn bind_upcall(driver_number: usize, subscribe_number: usize, userdata: usize) {
+    let key = (driver_number, subscribe_number);
+    with_upcall_wakers(|wakers| {
+        let slot = wakers
+            .bindings
+            .iter()
+            .position(|binding| matches!(binding, Some((bound_key, _)) if *bound_key == key))
+            .or_else(|| wakers.bindings.iter().position(Option::is_none));
+        // Without a free binding, upcalls cannot be attributed and wake all wakers.
+        if let Some(slot) = slot {
+            wakers.bindings[slot] = Some((key, userdata));
+        }
+    });
+}
+
+fn unbind_upcall(driver_number: usize, subscribe_number: usize) {
+    let key = (driver_number, subscribe_number);
+    with_upcall_wakers(|wakers| {
+        for binding in wakers.bindings.iter_mut() {
+            if matches!(binding, Some((bound_key, _)) if *bound_key == key) {
+                *binding = None;
+            }
+        }
+        // No more upcalls are delivered for the subscription.
+        for entry in wakers.wakers.iter_mut() {
+            if matches!(entry, Some((entry_key, _)) if *entry_key == key) {
+                *entry = None;
+            }
+        }
+    });
+}
+
+/// Called by the upcall trampoline of [syscalls::subscribe()] after the consumer has run. Upcalls
+/// which cannot be attributed to a single subscription wake all wakers waiting for upcalls.
+pub(crate) fn wake_upcall_wakers(userdata: usize) {
+    match with_upcall_wakers(|wakers| wakers.bound_key(userdata)) {
+        Some(bound_key) => wake_matching(|key| key == bound_key),
+        None => wake_matching(|key| key != ANY_UPCALL),
+    }
+}
+
+fn wake_matching(mut matches: impl FnMut((usize, usize)) -> bool) {
+    // The wakers are woken one by one outside of the registry as waking may register wakers.
+    while let Some(waker) = with_upcall_wakers(|wakers| wakers.take(&mut matches)) {
+        waker.wake();
     }
 }
@@ -26,7 +26,8 @@ where
     T: Termination,
 {
     main().check_result();
-    true // Need to return anything sized. Otherwise, a linker error pops up. (See https://github.com/tock/libtock-rs/issues/138)
+    // Let the kernel reclaim the process. Errors have already been reported by check_result.
+    syscalls::exit(0)
 }
 
 #[lang = "termination"]
@@ -38,10 +39,10 @@ impl Termination for () {
     fn check_result(self) {}
 }
 
-impl<S, T> Termination for Result<S, T> {
+impl<S, T: core::fmt::Debug> Termination for Result<S, T> {
     fn check_result(self) {
-        if self.is_err() {
-            unsafe { report_panic() };
+        if let Err(error) = self {
+            panic!("main returned an error: {:?}", error);
         }
     }
 }
@@ -52,11 +53,10 @@ unsafe fn panic_handler(_info: &core::panic::PanicInfo) -> ! {
     report_panic()
 }
 
+#[cfg(not(feature = "custom_panic_handler"))]
 unsafe fn report_panic() -> ! {
     // Signal a panic using the LowLevelDebug capsule (if available).
     let _ = syscalls::command1_insecure(8, 1, 1);
 
-    loop {
-        syscalls::raw::yieldk();
-    }
+    syscalls::exit(1)
 }
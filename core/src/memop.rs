@@ -1,8 +1,19 @@
-// Last updated for Tock 1.4.
+// Last updated for Tock 2.0.
 // See https://github.com/tock/tock/blob/master/doc/syscalls/memop.md
 use crate::syscalls;
+use crate::syscalls::CommandReturn;
 use core::slice;
 
+unsafe fn memop(operation: u32, arg: usize) -> CommandReturn {
+    let (r0, r1, r2, r3) = syscalls::raw::memop(operation, arg);
+    CommandReturn::from_registers(r0, r1, r2, r3)
+}
+
+/// Memory operations returning an address or a number. Returns 0 if the kernel reports a failure.
+fn memop_u32(operation: u32, arg: usize) -> usize {
+    unsafe { memop(operation, arg) }.success_u32().unwrap_or(0) as usize
+}
+
 /// Set the memory break
 /// # Safety
 /// Setting the break is marked as unsafe as it should only be called by the entry point to setup
@@ -10,50 +21,47 @@ use core::slice;
 ///
 /// Alternate allocator implementations may still find this useful in the future.
 pub unsafe fn set_brk(ptr: *const u8) -> bool {
-    syscalls::raw::memop(0, ptr as usize) == 0
+    memop(0, ptr as usize).is_success()
 }
 
 /// Increment the memory break
 pub fn increment_brk(increment: usize) -> Option<*const u8> {
-    let result = unsafe { syscalls::raw::memop(1, increment) };
-    if result >= 0 {
-        Some(result as *const u8)
-    } else {
-        None
-    }
+    unsafe { memop(1, increment) }
+        .success_u32()
+        .map(|previous_brk| previous_brk as usize as *const u8)
 }
 
 pub fn get_brk() -> *const u8 {
-    unsafe { syscalls::raw::memop(1, 0) as *const u8 }
+    memop_u32(1, 0) as *const u8
 }
 
 pub fn get_mem_start() -> *const u8 {
-    unsafe { syscalls::raw::memop(2, 0) as *const u8 }
+    memop_u32(2, 0) as *const u8
 }
 
 pub fn get_mem_end() -> *const u8 {
-    unsafe { syscalls::raw::memop(3, 0) as *const u8 }
+    memop_u32(3, 0) as *const u8
 }
 
 pub fn get_flash_start() -> *const u8 {
-    unsafe { syscalls::raw::memop(4, 0) as *const u8 }
+    memop_u32(4, 0) as *const u8
 }
 
 pub fn get_flash_end() -> *const u8 {
-    unsafe { syscalls::raw::memop(5, 0) as *const u8 }
+    memop_u32(5, 0) as *const u8
 }
 
 pub fn get_grant_start() -> *const u8 {
-    unsafe { syscalls::raw::memop(6, 0) as *const u8 }
+    memop_u32(6, 0) as *const u8
 }
 
 pub fn get_flash_regions_count() -> usize {
-    unsafe { syscalls::raw::memop(7, 0) as usize }
+    memop_u32(7, 0)
 }
 
 pub fn get_flash_region_start(i: usize) -> Option<*const u8> {
     if i < get_flash_regions_count() {
-        Some(unsafe { syscalls::raw::memop(8, i) as *const u8 })
+        Some(memop_u32(8, i) as *const u8)
     } else {
         None
     }
@@ -61,7 +69,7 @@ pub fn get_flash_region_start(i: usize) -> Option<*const u8> {
 
 pub fn get_flash_region_end(i: usize) -> Option<*const u8> {
     if i < get_flash_regions_count() {
-        Some(unsafe { syscalls::raw::memop(9, i) as *const u8 })
+        Some(memop_u32(9, i) as *const u8)
     } else {
         None
     }
@@ -71,9 +79,9 @@ pub fn get_flash_region_end(i: usize) -> Option<*const u8> {
 /// flash regions during the application's lifetime.
 pub fn get_flash_region(i: usize) -> Option<&'static [u8]> {
     if i < get_flash_regions_count() {
-        let start_addr = unsafe { syscalls::raw::memop(8, i) } as usize;
+        let start_addr = memop_u32(8, i);
         let start_ptr = start_addr as *const u8;
-        let end_addr = unsafe { syscalls::raw::memop(9, i) } as usize;
+        let end_addr = memop_u32(9, i);
         // This assumes that the kernel sends consistent results, i.e. start <= end.
         let len = end_addr - start_addr;
         Some(unsafe { slice::from_raw_parts(start_ptr, len) })
@@ -90,7 +98,7 @@ pub fn get_flash_region(i: usize) -> Option<&'static [u8]> {
 ///
 /// Alternate allocator implementations may still find this useful in the future.
 pub unsafe fn set_stack_top(ptr: *const u8) {
-    let _ = syscalls::raw::memop(10, ptr as usize);
+    let _ = memop(10, ptr as usize);
 }
 
 /// Set the top of the heap
@@ -99,5 +107,5 @@ pub unsafe fn set_stack_top(ptr: *const u8) {
 /// by the entry point to setup the allocator. Updating these values afterwards can lead to incorrect
 /// debug output from the kernel.
 pub unsafe fn set_heap_start(ptr: *const u8) {
-    let _ = syscalls::raw::memop(11, ptr as usize);
+    let _ = memop(11, ptr as usize);
 }
@@ -1,8 +1,11 @@
+use core::convert::TryFrom;
+use core::fmt;
+
 #[derive(Copy, Clone)]
 pub struct SubscribeError {
     pub driver_number: usize,
     pub subscribe_number: usize,
-    pub return_code: isize,
+    pub error_code: ErrorCode,
 }
 
 #[derive(Copy, Clone)]
@@ -11,20 +14,209 @@ pub struct CommandError {
     pub command_number: usize,
     pub arg1: usize,
     pub arg2: usize,
-    pub return_code: isize,
+    pub error_code: ErrorCode,
 }
 
 #[derive(Copy, Clone)]
 pub struct AllowError {
     pub driver_number: usize,
     pub allow_number: usize,
-    pub return_code: isize,
+    pub error_code: ErrorCode,
 }
 
+// The error types are printed on the device, so `Debug` is implemented by hand as the derived
+// implementations would add a lot of formatting code to the binary.
+
+impl fmt::Display for SubscribeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "subscribe {} of driver {} failed: {}",
+            self.subscribe_number, self.driver_number, self.error_code
+        )
+    }
+}
+
+impl fmt::Debug for SubscribeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "command {} of driver {} ({}, {}) failed: {}",
+            self.command_number, self.driver_number, self.arg1, self.arg2, self.error_code
+        )
+    }
+}
+
+impl fmt::Debug for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl fmt::Display for AllowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "allow {} of driver {} failed: {}",
+            self.allow_number, self.driver_number, self.error_code
+        )
+    }
+}
+
+impl fmt::Debug for AllowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+// Return codes of the Tock 1.x system call ABI. Prefer matching on [ErrorCode].
 pub const SUCCESS: isize = 0;
 pub const FAIL: isize = -1;
 pub const EBUSY: isize = -2;
 pub const EALREADY: isize = -3;
+pub const EOFF: isize = -4;
+pub const ERESERVE: isize = -5;
 pub const EINVAL: isize = -6;
 pub const ESIZE: isize = -7;
+pub const ECANCEL: isize = -8;
 pub const ENOMEM: isize = -9;
+pub const ENOSUPPORT: isize = -10;
+pub const ENODEVICE: isize = -11;
+pub const EUNINSTALLED: isize = -12;
+pub const ENOACK: isize = -13;
+
+/// Error codes of the Tock 2.0 system call ABI. They are returned by the kernel as part of the
+/// failure variants of [CommandReturn](crate::syscalls::CommandReturn).
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum ErrorCode {
+    Fail = 1,
+    Busy = 2,
+    Already = 3,
+    Off = 4,
+    Reserve = 5,
+    Invalid = 6,
+    Size = 7,
+    Cancel = 8,
+    NoMem = 9,
+    NoSupport = 10,
+    NoDevice = 11,
+    Uninstalled = 12,
+    NoAck = 13,
+    /// The kernel returned a variant which is not valid for the invoked system call.
+    BadRVal = 1024,
+}
+
+impl ErrorCode {
+    /// Decode the error code passed by the kernel. Returns `None` for unknown values.
+    pub fn from_usize(value: usize) -> Option<ErrorCode> {
+        let error_code = match value {
+            1 => ErrorCode::Fail,
+            2 => ErrorCode::Busy,
+            3 => ErrorCode::Already,
+            4 => ErrorCode::Off,
+            5 => ErrorCode::Reserve,
+            6 => ErrorCode::Invalid,
+            7 => ErrorCode::Size,
+            8 => ErrorCode::Cancel,
+            9 => ErrorCode::NoMem,
+            10 => ErrorCode::NoSupport,
+            11 => ErrorCode::NoDevice,
+            12 => ErrorCode::Uninstalled,
+            13 => ErrorCode::NoAck,
+            1024 => ErrorCode::BadRVal,
+            _ => return None,
+        };
+        Some(error_code)
+    }
+
+    /// The negative return code used by the Tock 1.x ABI, e.g. `EALREADY` for
+    /// `ErrorCode::Already`.
+    pub fn return_code(self) -> isize {
+        -(self as isize)
+    }
+
+    /// The name of the error code as used by the kernel, e.g. `EBUSY`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorCode::Fail => "FAIL",
+            ErrorCode::Busy => "EBUSY",
+            ErrorCode::Already => "EALREADY",
+            ErrorCode::Off => "EOFF",
+            ErrorCode::Reserve => "ERESERVE",
+            ErrorCode::Invalid => "EINVAL",
+            ErrorCode::Size => "ESIZE",
+            ErrorCode::Cancel => "ECANCEL",
+            ErrorCode::NoMem => "ENOMEM",
+            ErrorCode::NoSupport => "ENOSUPPORT",
+            ErrorCode::NoDevice => "ENODEVICE",
+            ErrorCode::Uninstalled => "EUNINSTALLED",
+            ErrorCode::NoAck => "ENOACK",
+            ErrorCode::BadRVal => "EBADRVAL",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl fmt::Debug for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl From<ErrorCode> for isize {
+    fn from(error_code: ErrorCode) -> Self {
+        error_code.return_code()
+    }
+}
+
+/// Converts a negative Tock 1.x return code, e.g. `EBUSY`, into an [ErrorCode].
+impl TryFrom<isize> for ErrorCode {
+    type Error = ();
+
+    fn try_from(return_code: isize) -> Result<Self, Self::Error> {
+        if return_code >= 0 {
+            return Err(());
+        }
+        ErrorCode::from_usize(return_code.wrapping_neg() as usize).ok_or(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn return_codes_convert_to_error_codes() {
+        assert_eq!(ErrorCode::try_from(EALREADY), Ok(ErrorCode::Already));
+        assert_eq!(ErrorCode::try_from(ENOACK), Ok(ErrorCode::NoAck));
+        assert_eq!(isize::from(ErrorCode::Busy), EBUSY);
+        assert_eq!(ErrorCode::try_from(SUCCESS), Err(()));
+        assert_eq!(ErrorCode::try_from(-14), Err(()));
+    }
+
+    #[test]
+    pub fn errors_are_displayed_with_decoded_error_code() {
+        let error = CommandError {
+            driver_number: 2,
+            command_number: 1,
+            arg1: 3,
+            arg2: 0,
+            error_code: ErrorCode::NoDevice,
+        };
+        assert_eq!(
+            format!("{}", error),
+            "command 1 of driver 2 (3, 0) failed: ENODEVICE"
+        );
+    }
+}
@@ -38,6 +38,42 @@ impl<'a> Drop for SharedMemory<'a> {
     }
 }
 
+/// Memory shared with the kernel using read-only allow. As the kernel cannot
+/// modify the buffer, it can be constant data, e.g. a `&'static [u8]` residing
+/// in flash.
+#[must_use = "Shared memory risks being dropped too early. Drop it manually."]
+pub struct ReadOnlySharedMemory<'a> {
+    driver_number: usize,
+    allow_number: usize,
+    buffer_to_share: &'a [u8],
+}
+
+impl<'a> ReadOnlySharedMemory<'a> {
+    pub fn new(
+        driver_number: usize,
+        allow_number: usize,
+        buffer_to_share: &'a [u8],
+    ) -> ReadOnlySharedMemory<'a> {
+        ReadOnlySharedMemory {
+            driver_number,
+            allow_number,
+            buffer_to_share,
+        }
+    }
+
+    pub fn read_bytes<T: AsMut<[u8]>>(&self, mut destination: T) {
+        safe_copy(self.buffer_to_share, destination.as_mut());
+    }
+}
+
+impl<'a> Drop for ReadOnlySharedMemory<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            syscalls::raw::allow_readonly(self.driver_number, self.allow_number, ptr::null(), 0);
+        }
+    }
+}
+
 fn safe_copy(origin: &[u8], destination: &mut [u8]) {
     let amount = origin.len().min(destination.len());
     let origin = &origin[0..amount];
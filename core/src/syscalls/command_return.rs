@@ -0,0 +1,179 @@
+// See https://github.com/tock/tock/blob/master/doc/reference/trd104-syscalls.md
+use crate::result::ErrorCode;
+
+mod return_variant {
+    pub const FAILURE: usize = 0;
+    pub const FAILURE_U32: usize = 1;
+    pub const FAILURE_U32_U32: usize = 2;
+    pub const FAILURE_U64: usize = 3;
+    pub const SUCCESS: usize = 128;
+    pub const SUCCESS_U32: usize = 129;
+    pub const SUCCESS_U32_U32: usize = 130;
+    pub const SUCCESS_U64: usize = 131;
+    pub const SUCCESS_U32_U32_U32: usize = 132;
+    pub const SUCCESS_U32_U64: usize = 133;
+}
+
+/// Value returned by the kernel in the Tock 2.0 system call ABI. The return
+/// variant is passed in the first register, the values in the remaining
+/// three. All system calls use the same encoding, although only `command` may
+/// return any of the variants.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CommandReturn {
+    Failure(ErrorCode),
+    FailureU32(ErrorCode, u32),
+    FailureU32U32(ErrorCode, u32, u32),
+    FailureU64(ErrorCode, u64),
+    Success,
+    SuccessU32(u32),
+    SuccessU32U32(u32, u32),
+    SuccessU64(u64),
+    SuccessU32U32U32(u32, u32, u32),
+    SuccessU32U64(u32, u64),
+}
+
+impl CommandReturn {
+    /// Decode the registers set by the kernel. Unknown return variants or error codes are decoded
+    /// as `Failure(ErrorCode::BadRVal)`.
+    pub fn from_registers(r0: usize, r1: usize, r2: usize, r3: usize) -> CommandReturn {
+        let error_code = || ErrorCode::from_usize(r1);
+        let decoded = match r0 {
+            return_variant::FAILURE => error_code().map(CommandReturn::Failure),
+            return_variant::FAILURE_U32 => {
+                error_code().map(|error_code| CommandReturn::FailureU32(error_code, r2 as u32))
+            }
+            return_variant::FAILURE_U32_U32 => error_code()
+                .map(|error_code| CommandReturn::FailureU32U32(error_code, r2 as u32, r3 as u32)),
+            return_variant::FAILURE_U64 => error_code()
+                .map(|error_code| CommandReturn::FailureU64(error_code, join_u64(r2, r3))),
+            return_variant::SUCCESS => Some(CommandReturn::Success),
+            return_variant::SUCCESS_U32 => Some(CommandReturn::SuccessU32(r1 as u32)),
+            return_variant::SUCCESS_U32_U32 => {
+                Some(CommandReturn::SuccessU32U32(r1 as u32, r2 as u32))
+            }
+            return_variant::SUCCESS_U64 => Some(CommandReturn::SuccessU64(join_u64(r1, r2))),
+            return_variant::SUCCESS_U32_U32_U32 => Some(CommandReturn::SuccessU32U32U32(
+                r1 as u32, r2 as u32, r3 as u32,
+            )),
+            return_variant::SUCCESS_U32_U64 => {
+                Some(CommandReturn::SuccessU32U64(r1 as u32, join_u64(r2, r3)))
+            }
+            _ => None,
+        };
+        decoded.unwrap_or(CommandReturn::Failure(ErrorCode::BadRVal))
+    }
+
+    /// Encode the value the way the kernel does. Used by the host platform.
+    pub fn to_registers(self) -> (usize, usize, usize, usize) {
+        match self {
+            CommandReturn::Failure(error_code) => {
+                (return_variant::FAILURE, error_code as usize, 0, 0)
+            }
+            CommandReturn::FailureU32(error_code, value) => (
+                return_variant::FAILURE_U32,
+                error_code as usize,
+                value as usize,
+                0,
+            ),
+            CommandReturn::FailureU32U32(error_code, value1, value2) => (
+                return_variant::FAILURE_U32_U32,
+                error_code as usize,
+                value1 as usize,
+                value2 as usize,
+            ),
+            CommandReturn::FailureU64(error_code, value) => {
+                let (low, high) = split_u64(value);
+                (return_variant::FAILURE_U64, error_code as usize, low, high)
+            }
+            CommandReturn::Success => (return_variant::SUCCESS, 0, 0, 0),
+            CommandReturn::SuccessU32(value) => (return_variant::SUCCESS_U32, value as usize, 0, 0),
+            CommandReturn::SuccessU32U32(value1, value2) => (
+                return_variant::SUCCESS_U32_U32,
+                value1 as usize,
+                value2 as usize,
+                0,
+            ),
+            CommandReturn::SuccessU64(value) => {
+                let (low, high) = split_u64(value);
+                (return_variant::SUCCESS_U64, low, high, 0)
+            }
+            CommandReturn::SuccessU32U32U32(value1, value2, value3) => (
+                return_variant::SUCCESS_U32_U32_U32,
+                value1 as usize,
+                value2 as usize,
+                value3 as usize,
+            ),
+            CommandReturn::SuccessU32U64(value1, value2) => {
+                let (low, high) = split_u64(value2);
+                (return_variant::SUCCESS_U32_U64, value1 as usize, low, high)
+            }
+        }
+    }
+
+    pub fn is_success(self) -> bool {
+        self.error_code().is_none()
+    }
+
+    /// Returns the error code of all failure variants.
+    pub fn error_code(self) -> Option<ErrorCode> {
+        match self {
+            CommandReturn::Failure(error_code)
+            | CommandReturn::FailureU32(error_code, _)
+            | CommandReturn::FailureU32U32(error_code, _, _)
+            | CommandReturn::FailureU64(error_code, _) => Some(error_code),
+            _ => None,
+        }
+    }
+
+    pub fn success_u32(self) -> Option<u32> {
+        match self {
+            CommandReturn::SuccessU32(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+fn join_u64(low: usize, high: usize) -> u64 {
+    (low as u32 as u64) | ((high as u32 as u64) << 32)
+}
+
+fn split_u64(value: u64) -> (usize, usize) {
+    (value as u32 as usize, (value >> 32) as u32 as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn all_variants_survive_encoding() {
+        let variants = [
+            CommandReturn::Failure(ErrorCode::Busy),
+            CommandReturn::FailureU32(ErrorCode::Size, 7),
+            CommandReturn::FailureU32U32(ErrorCode::NoAck, 1, 2),
+            CommandReturn::FailureU64(ErrorCode::Fail, 0x1234_5678_9abc_def0),
+            CommandReturn::Success,
+            CommandReturn::SuccessU32(42),
+            CommandReturn::SuccessU32U32(1, 2),
+            CommandReturn::SuccessU64(0xffff_ffff_0000_0001),
+            CommandReturn::SuccessU32U32U32(1, 2, 3),
+            CommandReturn::SuccessU32U64(5, 0x1_0000_0000),
+        ];
+        for &variant in variants.iter() {
+            let (r0, r1, r2, r3) = variant.to_registers();
+            assert_eq!(CommandReturn::from_registers(r0, r1, r2, r3), variant);
+        }
+    }
+
+    #[test]
+    pub fn unknown_variants_are_bad_return_values() {
+        assert_eq!(
+            CommandReturn::from_registers(4, 0, 0, 0),
+            CommandReturn::Failure(ErrorCode::BadRVal)
+        );
+        assert_eq!(
+            CommandReturn::from_registers(0, 42, 0, 0),
+            CommandReturn::Failure(ErrorCode::BadRVal)
+        );
+    }
+}
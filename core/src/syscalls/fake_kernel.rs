@@ -0,0 +1,640 @@
+//! A programmable fake kernel for host-side unit tests.
+//!
+//! While a [FakeKernel] is running (see [FakeKernel::run]), the host
+//! implementations of the functions in `syscalls::raw` are routed into it
+//! instead of returning the value configured by `run_recording_events`.
+//! Commands, subscriptions and allows are dispatched to fake drivers registered
+//! with [FakeKernel::add_driver]. Upcalls are queued either by the drivers
+//! themselves (e.g. a fake console signalling a finished write) or scripted by
+//! the test using [FakeKernel::schedule_upcall], and are delivered one at a time
+//! whenever the app yields. If the app waits for an upcall while none is queued,
+//! the drivers are notified using [FakeDriver::idle], e.g. to let simulated time
+//! pass. Exiting the process panics with the completion code.
+//!
+//! Example:
+//! ```
+//! # use libtock_core::syscalls;
+//! # use libtock_core::syscalls::fake_kernel::DriverHandle;
+//! # use libtock_core::syscalls::fake_kernel::FakeKernel;
+//! # use libtock_core::syscalls::CommandReturn;
+//! let mut kernel = FakeKernel::new();
+//! kernel.add_driver(
+//!     2,
+//!     |_: &mut DriverHandle, command_number: usize, _: usize, _: usize| {
+//!         if command_number == 0 {
+//!             CommandReturn::SuccessU32(4)
+//!         } else {
+//!             CommandReturn::Success
+//!         }
+//!     },
+//! );
+//! let num_leds = kernel.run(|| syscalls::command_u32(2, 0, 0, 0));
+//! assert_eq!(num_leds.ok(), Some(4));
+//! ```
+
+use super::CommandReturn;
+use crate::result::ErrorCode;
+use core::cell::RefCell;
+use core::mem;
+use core::slice;
+use std::boxed::Box;
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+
+type Callback = unsafe extern "C" fn(usize, usize, usize, usize);
+
+/// Address and length of shared buffers, keyed by driver and allow number.
+type Allows = BTreeMap<(usize, usize), (usize, usize)>;
+
+thread_local!(static KERNEL: RefCell<Option<FakeKernel>> = RefCell::new(None));
+
+/// A fake capsule which can be registered with a [FakeKernel].
+pub trait FakeDriver {
+    /// Handle a command. The return value is passed to the app unchanged.
+    fn command(
+        &mut self,
+        kernel: &mut DriverHandle,
+        command_number: usize,
+        arg1: usize,
+        arg2: usize,
+    ) -> CommandReturn;
+
+    /// Called after the app has (un)subscribed a callback. Returning an error
+    /// rejects the subscription.
+    fn subscribe(
+        &mut self,
+        _kernel: &mut DriverHandle,
+        _subscribe_number: usize,
+    ) -> Result<(), ErrorCode> {
+        Ok(())
+    }
+
+    /// Called after the app has shared (or unshared) a buffer using either
+    /// read-write or read-only allow. Returning an error rejects the buffer.
+    fn allow(
+        &mut self,
+        _kernel: &mut DriverHandle,
+        _allow_number: usize,
+        _len: usize,
+    ) -> Result<(), ErrorCode> {
+        Ok(())
+    }
+
+    /// Called if the app waits for an upcall while none is queued. Drivers simulating time can
+    /// let it pass until their next event and schedule its upcall.
+    fn idle(&mut self, _kernel: &mut DriverHandle) {}
+}
+
+impl<F: FnMut(&mut DriverHandle, usize, usize, usize) -> CommandReturn> FakeDriver for F {
+    fn command(
+        &mut self,
+        kernel: &mut DriverHandle,
+        command_number: usize,
+        arg1: usize,
+        arg2: usize,
+    ) -> CommandReturn {
+        self(kernel, command_number, arg1, arg2)
+    }
+}
+
+/// View of the kernel state given to a [FakeDriver] while it handles a system
+/// call.
+pub struct DriverHandle<'a> {
+    driver_number: usize,
+    state: &'a mut KernelState,
+}
+
+impl<'a> DriverHandle<'a> {
+    pub fn driver_number(&self) -> usize {
+        self.driver_number
+    }
+
+    /// Queue an upcall to the callback subscribed under `subscribe_number` of
+    /// this driver.
+    pub fn schedule_upcall(&mut self, subscribe_number: usize, args: (usize, usize, usize)) {
+        self.state
+            .schedule_upcall(self.driver_number, subscribe_number, args);
+    }
+
+    /// Check whether the app currently has a callback subscribed.
+    pub fn is_subscribed(&self, subscribe_number: usize) -> bool {
+        self.state
+            .subscriptions
+            .contains_key(&(self.driver_number, subscribe_number))
+    }
+
+    /// Access the buffer the app has currently shared under `allow_number`
+    /// using read-write allow.
+    pub fn allowed_buffer(&mut self, allow_number: usize) -> Option<&mut [u8]> {
+        let &(address, len) = self.state.allows.get(&(self.driver_number, allow_number))?;
+        // The app guarantees that the buffer stays valid until it is unshared, which removes it
+        // from the map.
+        Some(unsafe { slice::from_raw_parts_mut(address as *mut u8, len) })
+    }
+
+    /// Access the buffer the app has currently shared under `allow_number`
+    /// using read-only allow.
+    pub fn allowed_readonly_buffer(&self, allow_number: usize) -> Option<&[u8]> {
+        let &(address, len) = self
+            .state
+            .readonly_allows
+            .get(&(self.driver_number, allow_number))?;
+        Some(unsafe { slice::from_raw_parts(address as *const u8, len) })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Upcall {
+    driver_number: usize,
+    subscribe_number: usize,
+    args: (usize, usize, usize),
+}
+
+#[derive(Default)]
+struct KernelState {
+    subscriptions: BTreeMap<(usize, usize), (Callback, usize)>,
+    allows: Allows,
+    readonly_allows: Allows,
+    upcalls: VecDeque<Upcall>,
+}
+
+impl KernelState {
+    fn schedule_upcall(
+        &mut self,
+        driver_number: usize,
+        subscribe_number: usize,
+        args: (usize, usize, usize),
+    ) {
+        self.upcalls.push_back(Upcall {
+            driver_number,
+            subscribe_number,
+            args,
+        });
+    }
+
+    /// Pops upcalls until one with a subscribed callback is found. Upcalls without a callback are
+    /// dropped like the kernel does.
+    fn next_upcall(&mut self) -> Option<(Callback, Upcall, usize)> {
+        while let Some(upcall) = self.upcalls.pop_front() {
+            let key = (upcall.driver_number, upcall.subscribe_number);
+            if let Some(&(callback, userdata)) = self.subscriptions.get(&key) {
+                return Some((callback, upcall, userdata));
+            }
+        }
+        None
+    }
+}
+
+/// The fake kernel. See the module documentation for an example.
+pub struct FakeKernel {
+    drivers: BTreeMap<usize, Box<dyn FakeDriver>>,
+    memop: Box<dyn FnMut(u32, usize) -> CommandReturn>,
+    state: KernelState,
+}
+
+impl Default for FakeKernel {
+    fn default() -> Self {
+        FakeKernel::new()
+    }
+}
+
+impl FakeKernel {
+    /// Create a kernel without drivers. Memory operations fail with `NoSupport` unless a handler
+    /// is set using [FakeKernel::on_memop].
+    pub fn new() -> FakeKernel {
+        FakeKernel {
+            drivers: BTreeMap::new(),
+            memop: Box::new(|_, _| CommandReturn::Failure(ErrorCode::NoSupport)),
+            state: KernelState::default(),
+        }
+    }
+
+    /// Register a fake driver. System calls to driver numbers without a registered driver fail
+    /// with `NoDevice`.
+    pub fn add_driver<D: FakeDriver + 'static>(
+        &mut self,
+        driver_number: usize,
+        driver: D,
+    ) -> &mut Self {
+        self.drivers.insert(driver_number, Box::new(driver));
+        self
+    }
+
+    /// Set the handler for memory operations.
+    pub fn on_memop<F: FnMut(u32, usize) -> CommandReturn + 'static>(
+        &mut self,
+        handler: F,
+    ) -> &mut Self {
+        self.memop = Box::new(handler);
+        self
+    }
+
+    /// Script an upcall. It is delivered during a later yield if a callback is subscribed by then.
+    pub fn schedule_upcall(
+        &mut self,
+        driver_number: usize,
+        subscribe_number: usize,
+        args: (usize, usize, usize),
+    ) -> &mut Self {
+        self.state
+            .schedule_upcall(driver_number, subscribe_number, args);
+        self
+    }
+
+    /// Run the closure with all system calls of the current thread routed into this kernel.
+    pub fn run<R, F: FnOnce() -> R>(self, f: F) -> R {
+        struct Uninstall;
+
+        impl Drop for Uninstall {
+            fn drop(&mut self) {
+                // Avoid a double panic if the closure panicked while the kernel was borrowed.
+                let _ = KERNEL.try_with(|k| k.try_borrow_mut().map(|mut k| k.take()));
+            }
+        }
+
+        KERNEL.with(|k| {
+            let previous = k.borrow_mut().replace(self);
+            assert!(previous.is_none(), "A fake kernel is already running");
+        });
+        let _uninstall = Uninstall;
+        f()
+    }
+
+    fn dispatch<R, F: FnOnce(&mut dyn FakeDriver, &mut DriverHandle) -> R>(
+        &mut self,
+        driver_number: usize,
+        f: F,
+    ) -> Option<R> {
+        let driver = self.drivers.get_mut(&driver_number)?;
+        Some(f(
+            driver.as_mut(),
+            &mut DriverHandle {
+                driver_number,
+                state: &mut self.state,
+            },
+        ))
+    }
+
+    fn idle(&mut self) {
+        for (&driver_number, driver) in self.drivers.iter_mut() {
+            driver.idle(&mut DriverHandle {
+                driver_number,
+                state: &mut self.state,
+            });
+        }
+    }
+
+    fn command(
+        &mut self,
+        driver_number: usize,
+        command_number: usize,
+        arg1: usize,
+        arg2: usize,
+    ) -> CommandReturn {
+        self.dispatch(driver_number, |driver, handle| {
+            driver.command(handle, command_number, arg1, arg2)
+        })
+        .unwrap_or(CommandReturn::Failure(ErrorCode::NoDevice))
+    }
+
+    fn subscribe(
+        &mut self,
+        driver_number: usize,
+        subscribe_number: usize,
+        callback: *const Callback,
+        userdata: usize,
+    ) -> CommandReturn {
+        if !self.drivers.contains_key(&driver_number) {
+            return CommandReturn::Failure(ErrorCode::NoDevice);
+        }
+        let key = (driver_number, subscribe_number);
+        let previous = if callback.is_null() {
+            self.state.subscriptions.remove(&key)
+        } else {
+            // `syscalls::subscribe_fn` passes the function itself, not a pointer to it.
+            let callback = unsafe { mem::transmute::<*const (), Callback>(callback as *const ()) };
+            self.state.subscriptions.insert(key, (callback, userdata))
+        };
+        let result = self
+            .dispatch(driver_number, |driver, handle| {
+                driver.subscribe(handle, subscribe_number)
+            })
+            .unwrap_or(Ok(()));
+        swap_result(
+            &mut self.state.subscriptions,
+            key,
+            previous,
+            result,
+            |&(callback, userdata)| (callback as usize, userdata),
+        )
+    }
+
+    fn allow(
+        &mut self,
+        allows: fn(&mut KernelState) -> &mut Allows,
+        driver_number: usize,
+        allow_number: usize,
+        address: usize,
+        len: usize,
+    ) -> CommandReturn {
+        if !self.drivers.contains_key(&driver_number) {
+            return CommandReturn::Failure(ErrorCode::NoDevice);
+        }
+        let key = (driver_number, allow_number);
+        let previous = if address == 0 {
+            allows(&mut self.state).remove(&key)
+        } else {
+            allows(&mut self.state).insert(key, (address, len))
+        };
+        let result = self
+            .dispatch(driver_number, |driver, handle| {
+                driver.allow(handle, allow_number, len)
+            })
+            .unwrap_or(Ok(()));
+        swap_result(allows(&mut self.state), key, previous, result, |&value| {
+            value
+        })
+    }
+}
+
+/// Computes the return value of subscribe and allow, which return the previous value on success.
+/// Restores the previous value if the driver rejected the new one.
+fn swap_result<V>(
+    map: &mut BTreeMap<(usize, usize), V>,
+    key: (usize, usize),
+    previous: Option<V>,
+    result: Result<(), ErrorCode>,
+    to_u32s: fn(&V) -> (usize, usize),
+) -> CommandReturn {
+    match result {
+        Ok(()) => {
+            let (value1, value2) = previous.as_ref().map(to_u32s).unwrap_or((0, 0));
+            CommandReturn::SuccessU32U32(value1 as u32, value2 as u32)
+        }
+        Err(error_code) => {
+            match previous {
+                Some(value) => map.insert(key, value),
+                None => map.remove(&key),
+            };
+            CommandReturn::Failure(error_code)
+        }
+    }
+}
+
+fn with_kernel<R, F: FnOnce(&mut FakeKernel) -> R>(f: F) -> Option<R> {
+    KERNEL.with(|k| k.borrow_mut().as_mut().map(f))
+}
+
+// The functions below are called by the host platform. They return `None` if no fake kernel is
+// running.
+
+pub(super) fn yieldk() -> Option<()> {
+    if with_kernel(|kernel| kernel.state.upcalls.is_empty())? {
+        with_kernel(FakeKernel::idle);
+    }
+    let upcall_invoked = yield_no_wait()?;
+    assert!(
+        upcall_invoked,
+        "yieldk called without pending upcalls: the app would sleep forever"
+    );
+    Some(())
+}
+
+pub(super) fn yield_no_wait() -> Option<bool> {
+    let (callback, upcall, userdata) = match with_kernel(|kernel| kernel.state.next_upcall())? {
+        Some(next_upcall) => next_upcall,
+        None => return Some(false),
+    };
+    // The kernel must not be borrowed while the callback runs as it may issue system calls itself.
+    let (arg1, arg2, arg3) = upcall.args;
+    unsafe { callback(arg1, arg2, arg3, userdata) };
+    Some(true)
+}
+
+pub(super) fn subscribe(
+    driver_number: usize,
+    subscribe_number: usize,
+    callback: *const Callback,
+    userdata: usize,
+) -> Option<CommandReturn> {
+    with_kernel(|kernel| kernel.subscribe(driver_number, subscribe_number, callback, userdata))
+}
+
+pub(super) fn command(
+    driver_number: usize,
+    command_number: usize,
+    arg1: usize,
+    arg2: usize,
+) -> Option<CommandReturn> {
+    with_kernel(|kernel| kernel.command(driver_number, command_number, arg1, arg2))
+}
+
+pub(super) fn allow(
+    driver_number: usize,
+    allow_number: usize,
+    ptr: *mut u8,
+    len: usize,
+) -> Option<CommandReturn> {
+    with_kernel(|kernel| {
+        kernel.allow(
+            |state| &mut state.allows,
+            driver_number,
+            allow_number,
+            ptr as usize,
+            len,
+        )
+    })
+}
+
+pub(super) fn allow_readonly(
+    driver_number: usize,
+    allow_number: usize,
+    ptr: *const u8,
+    len: usize,
+) -> Option<CommandReturn> {
+    with_kernel(|kernel| {
+        kernel.allow(
+            |state| &mut state.readonly_allows,
+            driver_number,
+            allow_number,
+            ptr as usize,
+            len,
+        )
+    })
+}
+
+pub(super) fn memop(operation: u32, arg: usize) -> Option<CommandReturn> {
+    with_kernel(|kernel| (kernel.memop)(operation, arg))
+}
+
+pub(super) fn exit(exit_number: usize, completion_code: usize) {
+    if with_kernel(|_| ()).is_some() {
+        match exit_number {
+            0 => panic!(
+                "Process terminated with completion code {}",
+                completion_code
+            ),
+            _ => panic!("Process restarted with completion code {}", completion_code),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::callback::Identity2Consumer;
+    use crate::syscalls;
+    use core::cell::Cell;
+    use std::rc::Rc;
+
+    const DRIVER_NUMBER: usize = 0x1234;
+
+    #[test]
+    pub fn unknown_driver_returns_nodevice() {
+        let result = FakeKernel::new().run(|| syscalls::command_return(DRIVER_NUMBER, 0, 0, 0));
+        assert_eq!(result, CommandReturn::Failure(ErrorCode::NoDevice));
+    }
+
+    #[test]
+    pub fn scripted_upcalls_are_delivered_in_order_on_yield() {
+        let mut kernel = FakeKernel::new();
+        kernel
+            .add_driver(DRIVER_NUMBER, |_: &mut DriverHandle, _, _, _| {
+                CommandReturn::Success
+            })
+            .schedule_upcall(DRIVER_NUMBER, 0, (1, 2, 0))
+            .schedule_upcall(DRIVER_NUMBER, 1, (5, 5, 0))
+            .schedule_upcall(DRIVER_NUMBER, 0, (3, 4, 0));
+
+        let received = kernel.run(|| {
+            let mut received = Vec::new();
+            let mut callback = |arg1, arg2| received.push((arg1, arg2));
+            let subscription =
+                syscalls::subscribe::<Identity2Consumer, _>(DRIVER_NUMBER, 0, &mut callback)
+                    .ok()
+                    .unwrap();
+            unsafe {
+                syscalls::raw::yieldk();
+                syscalls::raw::yieldk();
+            }
+            mem::drop(subscription);
+            received
+        });
+
+        assert_eq!(received, vec![(1, 2), (3, 4)]);
+    }
+
+    #[test]
+    pub fn yield_no_wait_returns_whether_an_upcall_was_delivered() {
+        let mut kernel = FakeKernel::new();
+        kernel
+            .add_driver(DRIVER_NUMBER, |_: &mut DriverHandle, _, _, _| {
+                CommandReturn::Success
+            })
+            .schedule_upcall(DRIVER_NUMBER, 0, (0, 0, 0));
+
+        let upcalls_invoked = kernel.run(|| {
+            let mut callback = |_, _| {};
+            let _subscription =
+                syscalls::subscribe::<Identity2Consumer, _>(DRIVER_NUMBER, 0, &mut callback)
+                    .ok()
+                    .unwrap();
+            unsafe {
+                [
+                    syscalls::raw::yield_no_wait(),
+                    syscalls::raw::yield_no_wait(),
+                ]
+            }
+        });
+
+        assert_eq!(upcalls_invoked, [true, false]);
+    }
+
+    #[test]
+    #[should_panic(expected = "sleep forever")]
+    pub fn yield_without_upcalls_panics() {
+        FakeKernel::new().run(|| unsafe { syscalls::raw::yieldk() });
+    }
+
+    #[test]
+    pub fn drivers_are_notified_when_the_app_waits_for_upcalls() {
+        struct FakeAlarm;
+
+        impl FakeDriver for FakeAlarm {
+            fn command(
+                &mut self,
+                _: &mut DriverHandle,
+                _: usize,
+                _: usize,
+                _: usize,
+            ) -> CommandReturn {
+                CommandReturn::Success
+            }
+
+            fn idle(&mut self, kernel: &mut DriverHandle) {
+                kernel.schedule_upcall(0, (7, 0, 0));
+            }
+        }
+
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, FakeAlarm);
+        let fired_at = kernel.run(|| {
+            let fired_at = Cell::new(None);
+            let mut callback = |now, _| fired_at.set(Some(now));
+            let _subscription =
+                syscalls::subscribe::<Identity2Consumer, _>(DRIVER_NUMBER, 0, &mut callback)
+                    .unwrap();
+            let upcall_delivered = unsafe { syscalls::raw::yield_no_wait() };
+            assert!(!upcall_delivered);
+            unsafe { syscalls::raw::yieldk() };
+            fired_at.get()
+        });
+        assert_eq!(fired_at, Some(7));
+    }
+
+    #[test]
+    #[should_panic(expected = "Process terminated with completion code 3")]
+    pub fn exit_terminates_the_process() {
+        FakeKernel::new().run(|| syscalls::exit(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Process restarted with completion code 0")]
+    pub fn restart_restarts_the_process() {
+        FakeKernel::new().run(|| syscalls::restart(0));
+    }
+
+    #[test]
+    pub fn drivers_can_access_allowed_buffers_and_schedule_upcalls() {
+        let written = Rc::new(Cell::new(0));
+        let written_in_driver = written.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, move |handle: &mut DriverHandle, _, _, _| {
+            let buffer = handle.allowed_buffer(0).unwrap();
+            buffer[0] = 42;
+            written_in_driver.set(buffer.len());
+            handle.schedule_upcall(0, (0, 0, 0));
+            CommandReturn::Success
+        });
+
+        let (buffer, done) = kernel.run(|| {
+            let mut buffer = [0; 4];
+            let done = Cell::new(false);
+            let mut callback = |_, _| done.set(true);
+            let shared_memory = syscalls::allow(DRIVER_NUMBER, 0, &mut buffer).ok().unwrap();
+            let subscription =
+                syscalls::subscribe::<Identity2Consumer, _>(DRIVER_NUMBER, 0, &mut callback)
+                    .ok()
+                    .unwrap();
+            syscalls::command(DRIVER_NUMBER, 1, 0, 0).ok().unwrap();
+            unsafe { syscalls::raw::yieldk() };
+            mem::drop(subscription);
+            mem::drop(shared_memory);
+            (buffer, done.get())
+        });
+
+        assert_eq!(buffer, [42, 0, 0, 0]);
+        assert_eq!(written.get(), 4);
+        assert!(done);
+    }
+}
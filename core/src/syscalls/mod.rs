@@ -1,18 +1,38 @@
+// Implements the Tock 2.0 system call ABI.
+// See https://github.com/tock/tock/blob/master/doc/reference/trd104-syscalls.md
 #[cfg_attr(target_arch = "riscv32", path = "platform_riscv32.rs")]
 #[cfg_attr(target_arch = "arm", path = "platform_arm.rs")]
 mod platform;
 
+mod command_return;
+
+#[cfg(all(
+    feature = "fake_kernel",
+    not(any(target_arch = "arm", target_arch = "riscv32"))
+))]
+pub mod fake_kernel;
+
+use crate::callback;
 use crate::callback::CallbackSubscription;
 use crate::callback::Consumer;
 use crate::result::AllowError;
 use crate::result::CommandError;
+use crate::result::ErrorCode;
 use crate::result::SubscribeError;
+use crate::shared_memory::ReadOnlySharedMemory;
 use crate::shared_memory::SharedMemory;
 
+pub use command_return::CommandReturn;
+
 pub mod raw {
     pub use super::platform::*;
 }
 
+mod exit_nr {
+    pub const TERMINATE: usize = 0;
+    pub const RESTART: usize = 1;
+}
+
 pub fn subscribe<C: Consumer<T>, T>(
     driver_number: usize,
     subscribe_number: usize,
@@ -26,15 +46,18 @@ pub fn subscribe<C: Consumer<T>, T>(
     ) {
         let payload = unsafe { &mut *(data as *mut T) };
         C::consume(payload, arg1, arg2, arg3);
+        callback::wake_upcall_wakers(data);
     }
 
+    let userdata = payload as *mut _ as usize;
     subscribe_fn(
         driver_number,
         subscribe_number,
         c_callback::<T, C>,
-        payload as *mut _ as usize,
-    )
-    .map(|_| CallbackSubscription::new(driver_number, subscribe_number))
+        userdata,
+    )?;
+    callback::bind_upcall(driver_number, subscribe_number, userdata);
+    Ok(CallbackSubscription::new(driver_number, subscribe_number))
 }
 
 pub fn subscribe_fn(
@@ -43,7 +66,7 @@ pub fn subscribe_fn(
     callback: extern "C" fn(usize, usize, usize, usize),
     userdata: usize,
 ) -> Result<(), SubscribeError> {
-    let return_code = unsafe {
+    let (r0, r1, r2, r3) = unsafe {
         raw::subscribe(
             driver_number,
             subscribe_number,
@@ -52,34 +75,63 @@ pub fn subscribe_fn(
         )
     };
 
-    if return_code == 0 {
-        Ok(())
-    } else {
-        Err(SubscribeError {
+    // The previous callback returned on success is of no use to us.
+    match CommandReturn::from_registers(r0, r1, r2, r3).error_code() {
+        None => Ok(()),
+        Some(error_code) => Err(SubscribeError {
             driver_number,
             subscribe_number,
-            return_code,
-        })
+            error_code,
+        }),
     }
 }
 
+/// Issue a command and return the typed return variant passed by the kernel.
+pub fn command_return(
+    driver_number: usize,
+    command_number: usize,
+    arg1: usize,
+    arg2: usize,
+) -> CommandReturn {
+    let (r0, r1, r2, r3) = unsafe { raw::command(driver_number, command_number, arg1, arg2) };
+    CommandReturn::from_registers(r0, r1, r2, r3)
+}
+
+/// Issue a command which is expected to return `Success`.
 pub fn command(
     driver_number: usize,
     command_number: usize,
     arg1: usize,
     arg2: usize,
-) -> Result<usize, CommandError> {
-    let return_code = unsafe { raw::command(driver_number, command_number, arg1, arg2) };
-    if return_code >= 0 {
-        Ok(return_code as usize)
-    } else {
-        Err(CommandError {
+) -> Result<(), CommandError> {
+    match command_return(driver_number, command_number, arg1, arg2) {
+        CommandReturn::Success => Ok(()),
+        other => Err(command_error(
+            driver_number,
+            command_number,
+            arg1,
+            arg2,
+            other,
+        )),
+    }
+}
+
+/// Issue a command which is expected to return `SuccessU32`.
+pub fn command_u32(
+    driver_number: usize,
+    command_number: usize,
+    arg1: usize,
+    arg2: usize,
+) -> Result<u32, CommandError> {
+    match command_return(driver_number, command_number, arg1, arg2) {
+        CommandReturn::SuccessU32(value) => Ok(value),
+        other => Err(command_error(
             driver_number,
             command_number,
             arg1,
             arg2,
-            return_code,
-        })
+            other,
+        )),
     }
 }
 
@@ -97,18 +149,29 @@ pub fn command1_insecure(
     driver_number: usize,
     command_number: usize,
     arg: usize,
-) -> Result<usize, CommandError> {
-    let return_code = unsafe { raw::command1(driver_number, command_number, arg) };
-    if return_code >= 0 {
-        Ok(return_code as usize)
-    } else {
-        Err(CommandError {
-            driver_number,
-            command_number,
-            arg1: arg,
-            arg2: 0,
-            return_code,
-        })
+) -> Result<(), CommandError> {
+    let (r0, r1, r2, r3) = unsafe { raw::command1(driver_number, command_number, arg) };
+    match CommandReturn::from_registers(r0, r1, r2, r3) {
+        CommandReturn::Success => Ok(()),
+        other => Err(command_error(driver_number, command_number, arg, 0, other)),
+    }
+}
+
+fn command_error(
+    driver_number: usize,
+    command_number: usize,
+    arg1: usize,
+    arg2: usize,
+    unexpected: CommandReturn,
+) -> CommandError {
+    // A success variant other than the expected one is a bad return value.
+    let error_code = unexpected.error_code().unwrap_or(ErrorCode::BadRVal);
+    CommandError {
+        driver_number,
+        command_number,
+        arg1,
+        arg2,
+        error_code,
     }
 }
 
@@ -118,7 +181,7 @@ pub fn allow(
     buffer_to_share: &mut [u8],
 ) -> Result<SharedMemory, AllowError> {
     let len = buffer_to_share.len();
-    let return_code = unsafe {
+    let (r0, r1, r2, r3) = unsafe {
         raw::allow(
             driver_number,
             allow_number,
@@ -126,17 +189,64 @@ pub fn allow(
             len,
         )
     };
-    if return_code == 0 {
-        Ok(SharedMemory::new(
+    match CommandReturn::from_registers(r0, r1, r2, r3).error_code() {
+        None => Ok(SharedMemory::new(
             driver_number,
             allow_number,
             buffer_to_share,
-        ))
-    } else {
-        Err(AllowError {
+        )),
+        Some(error_code) => Err(AllowError {
             driver_number,
             allow_number,
-            return_code,
-        })
+            error_code,
+        }),
+    }
+}
+
+/// Share a buffer the kernel may only read from. Unlike [allow()], it does not
+/// require mutable access, so constant data can be shared without copying it to
+/// RAM first.
+pub fn allow_readonly(
+    driver_number: usize,
+    allow_number: usize,
+    buffer_to_share: &[u8],
+) -> Result<ReadOnlySharedMemory, AllowError> {
+    let len = buffer_to_share.len();
+    let (r0, r1, r2, r3) =
+        unsafe { raw::allow_readonly(driver_number, allow_number, buffer_to_share.as_ptr(), len) };
+    match CommandReturn::from_registers(r0, r1, r2, r3).error_code() {
+        None => Ok(ReadOnlySharedMemory::new(
+            driver_number,
+            allow_number,
+            buffer_to_share,
+        )),
+        Some(error_code) => Err(AllowError {
+            driver_number,
+            allow_number,
+            error_code,
+        }),
+    }
+}
+
+/// Terminate the process, passing the completion code to the kernel. Destructors of live values
+/// are not executed.
+pub fn exit(completion_code: u32) -> ! {
+    exit_with(exit_nr::TERMINATE, completion_code)
+}
+
+/// Ask the kernel to restart the process, passing the completion code. Destructors of live
+/// values are not executed.
+pub fn restart(completion_code: u32) -> ! {
+    exit_with(exit_nr::RESTART, completion_code)
+}
+
+fn exit_with(exit_number: usize, completion_code: u32) -> ! {
+    unsafe {
+        let _ = raw::exit(exit_number, completion_code as usize);
+    }
+    // Tock 1.x kernels do not implement the exit system call and return. As a fallback, the
+    // process sleeps forever.
+    loop {
+        unsafe { raw::yieldk() };
     }
 }
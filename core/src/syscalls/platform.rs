@@ -1,8 +1,9 @@
+use super::CommandReturn;
 use core::cell::Cell;
 use core::cell::RefCell;
 use std::vec::Vec;
 
-/// yield for a callback fired by the kernel
+/// yield-wait: yield until a callback fired by the kernel has been executed
 ///
 /// # Safety
 ///
@@ -14,6 +15,25 @@ use std::vec::Vec;
 /// - Callbacks can get executed in a nested manner and overflow the stack quickly.
 pub unsafe fn yieldk() {
     EVENTS.with(|e| e.borrow_mut().push(Event::YieldK));
+    #[cfg(feature = "fake_kernel")]
+    super::fake_kernel::yieldk();
+}
+
+/// yield-no-wait: execute a pending callback if there is one, but return
+/// immediately otherwise. Returns whether a callback has been executed.
+///
+/// # Safety
+///
+/// See [yieldk()].
+pub unsafe fn yield_no_wait() -> bool {
+    EVENTS.with(|e| e.borrow_mut().push(Event::YieldNoWait));
+    #[cfg(feature = "fake_kernel")]
+    {
+        if let Some(upcall_invoked) = super::fake_kernel::yield_no_wait() {
+            return upcall_invoked;
+        }
+    }
+    false
 }
 
 /// Subscribe a callback to the kernel
@@ -24,62 +44,140 @@ pub unsafe fn subscribe(
     arg2: usize,
     arg3: *const unsafe extern "C" fn(usize, usize, usize, usize),
     arg4: usize,
-) -> isize {
+) -> (usize, usize, usize, usize) {
     EVENTS.with(|e| {
         e.borrow_mut()
             .push(Event::Subscribe(arg1, arg2, arg3, arg4))
     });
-    NEXT_OUTPUT.with(|e| e.get())
+    #[cfg(feature = "fake_kernel")]
+    {
+        if let Some(return_value) = super::fake_kernel::subscribe(arg1, arg2, arg3, arg4) {
+            return return_value.to_registers();
+        }
+    }
+    next_output()
 }
 
 /// Send a command to the tock kernel
 /// # Safety
 /// This function usually involves assembly calls which are unsafe.
-pub unsafe fn command(arg1: usize, arg2: usize, arg3: usize, arg4: usize) -> isize {
+pub unsafe fn command(
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+) -> (usize, usize, usize, usize) {
     EVENTS.with(|e| e.borrow_mut().push(Event::Command(arg1, arg2, arg3, arg4)));
-    NEXT_OUTPUT.with(|e| e.get())
+    #[cfg(feature = "fake_kernel")]
+    {
+        if let Some(return_value) = super::fake_kernel::command(arg1, arg2, arg3, arg4) {
+            return return_value.to_registers();
+        }
+    }
+    next_output()
 }
 
 /// Call a command only taking into accoun the first argument
 /// # Safety
 /// Unsafe as ignored arguments cause leaking of registers to the kernel
-pub unsafe fn command1(arg1: usize, arg2: usize, arg3: usize) -> isize {
+pub unsafe fn command1(arg1: usize, arg2: usize, arg3: usize) -> (usize, usize, usize, usize) {
     EVENTS.with(|e| e.borrow_mut().push(Event::Command1(arg1, arg2, arg3)));
-    NEXT_OUTPUT.with(|e| e.get())
+    #[cfg(feature = "fake_kernel")]
+    {
+        if let Some(return_value) = super::fake_kernel::command(arg1, arg2, arg3, 0) {
+            return return_value.to_registers();
+        }
+    }
+    next_output()
 }
 
-/// Share a memory region with the kernel
+/// Share a memory region with the kernel (read-write allow)
 /// # Safety
 /// Unsafe as the pointer to the shared buffer is potentially dereferenced by the kernel.
-pub unsafe fn allow(arg1: usize, arg2: usize, arg3: *mut u8, arg4: usize) -> isize {
+pub unsafe fn allow(
+    arg1: usize,
+    arg2: usize,
+    arg3: *mut u8,
+    arg4: usize,
+) -> (usize, usize, usize, usize) {
     EVENTS.with(|e| e.borrow_mut().push(Event::Allow(arg1, arg2, arg3, arg4)));
-    NEXT_OUTPUT.with(|e| e.get())
+    #[cfg(feature = "fake_kernel")]
+    {
+        if let Some(return_value) = super::fake_kernel::allow(arg1, arg2, arg3, arg4) {
+            return return_value.to_registers();
+        }
+    }
+    next_output()
+}
+
+/// Share a memory region with the kernel which the kernel may only read (read-only allow)
+/// # Safety
+/// Unsafe as the pointer to the shared buffer is potentially dereferenced by the kernel.
+pub unsafe fn allow_readonly(
+    arg1: usize,
+    arg2: usize,
+    arg3: *const u8,
+    arg4: usize,
+) -> (usize, usize, usize, usize) {
+    EVENTS.with(|e| {
+        e.borrow_mut()
+            .push(Event::AllowReadOnly(arg1, arg2, arg3, arg4))
+    });
+    #[cfg(feature = "fake_kernel")]
+    {
+        if let Some(return_value) = super::fake_kernel::allow_readonly(arg1, arg2, arg3, arg4) {
+            return return_value.to_registers();
+        }
+    }
+    next_output()
 }
 
 /// Generic operations on the app's memory as requesting more memory
 /// # Safety
 /// Allows the kernel to do generic operations on the app's memory which can cause memory corruption.
-pub unsafe fn memop(arg1: u32, arg2: usize) -> isize {
+pub unsafe fn memop(arg1: u32, arg2: usize) -> (usize, usize, usize, usize) {
     EVENTS.with(|e| e.borrow_mut().push(Event::Memop(arg1, arg2)));
-    NEXT_OUTPUT.with(|e| e.get())
+    #[cfg(feature = "fake_kernel")]
+    {
+        if let Some(return_value) = super::fake_kernel::memop(arg1, arg2) {
+            return return_value.to_registers();
+        }
+    }
+    next_output()
+}
+
+/// Terminate (exit number 0) or restart (exit number 1) the process. Only returns if the kernel
+/// rejects the request.
+/// # Safety
+/// Unsafe as destructors of live values are not executed.
+pub unsafe fn exit(arg1: usize, arg2: usize) -> (usize, usize, usize, usize) {
+    EVENTS.with(|e| e.borrow_mut().push(Event::Exit(arg1, arg2)));
+    #[cfg(feature = "fake_kernel")]
+    super::fake_kernel::exit(arg1, arg2);
+    next_output()
 }
 
 /// For tests: Run the closure recording the syscalls which are invoked in during the run of the closure.
 pub fn run_recording_events<R, C: FnMut(&NextReturn) -> R>(mut f: C) -> Vec<Event> {
-    NEXT_OUTPUT.with(|n| n.set(0));
+    NEXT_OUTPUT.with(|n| n.set(CommandReturn::Success));
     NEXT_OUTPUT.with(|n| f(n));
     let mut output = Vec::new();
     EVENTS.with(|e| output.append(&mut e.borrow_mut()));
     output
 }
 
+fn next_output() -> (usize, usize, usize, usize) {
+    NEXT_OUTPUT.with(|e| e.get()).to_registers()
+}
+
 thread_local!(static EVENTS: RefCell<Vec<Event>> = RefCell::new(Vec::new()));
-thread_local!(static NEXT_OUTPUT: NextReturn = NextReturn { next_return: Cell::new(0) });
+thread_local!(static NEXT_OUTPUT: NextReturn = NextReturn { next_return: Cell::new(CommandReturn::Success) });
 
 #[derive(Clone, Debug, PartialEq)]
 /// For tests: syscall event
 pub enum Event {
     YieldK,
+    YieldNoWait,
     Subscribe(
         usize,
         usize,
@@ -89,21 +187,23 @@ pub enum Event {
     Command(usize, usize, usize, usize),
     Command1(usize, usize, usize),
     Allow(usize, usize, *mut u8, usize),
+    AllowReadOnly(usize, usize, *const u8, usize),
     Memop(u32, usize),
+    Exit(usize, usize),
 }
 
 /// For tests: controls the next return value of any syscall
 pub struct NextReturn {
-    next_return: Cell<isize>,
+    next_return: Cell<CommandReturn>,
 }
 
 impl NextReturn {
     /// Set the next return value
-    pub fn set(&self, value: isize) {
+    pub fn set(&self, value: CommandReturn) {
         self.next_return.set(value);
     }
 
-    fn get(&self) -> isize {
+    fn get(&self) -> CommandReturn {
         self.next_return.get()
     }
 }
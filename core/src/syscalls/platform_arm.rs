@@ -22,12 +22,33 @@ pub unsafe fn yieldk() {
     // registers r4-r8, r10, r11 and SP (and r9 in PCS variants that designate
     // r9 as v6) As our compilation flags mark r9 as the PIC base register, it
     // does not need to be saved. Thus we must clobber r0-3, r12, and LR
-    asm!(
-            "svc 0"
-            :
-            :
-            : "memory", "r0", "r1", "r2", "r3", "r12", "lr"
-            : "volatile");
+    //
+    // r0 selects yield-wait (1). It is declared as an output to mark it as
+    // clobbered.
+    let _r0: usize;
+    asm!("svc 0"
+         : "={r0}"(_r0)
+         : "{r0}"(1usize)
+         : "memory", "r1", "r2", "r3", "r12", "lr"
+         : "volatile");
+}
+
+#[inline(always)]
+// Justification: documentation is generated from mocks
+#[allow(clippy::missing_safety_doc)]
+pub unsafe fn yield_no_wait() -> bool {
+    // See yieldk() for the clobbered registers. r0 selects yield-no-wait (0),
+    // the kernel stores whether a callback has been executed at the address
+    // passed in r1.
+    let mut upcall_invoked: u8 = 0;
+    let _r0: usize;
+    let _r1: usize;
+    asm!("svc 0"
+         : "={r0}"(_r0), "={r1}"(_r1)
+         : "{r0}"(0usize), "{r1}"(&mut upcall_invoked as *mut u8)
+         : "memory", "r2", "r3", "r12", "lr"
+         : "volatile");
+    upcall_invoked != 0
 }
 
 #[inline(always)]
@@ -38,59 +59,98 @@ pub unsafe fn subscribe(
     minor: usize,
     cb: *const unsafe extern "C" fn(usize, usize, usize, usize),
     ud: usize,
-) -> isize {
-    let res;
-    asm!("svc 1" : "={r0}"(res)
-                 : "{r0}"(major) "{r1}"(minor) "{r2}"(cb) "{r3}"(ud)
+) -> (usize, usize, usize, usize) {
+    let (r0, r1, r2, r3);
+    asm!("svc 1" : "={r0}"(r0), "={r1}"(r1), "={r2}"(r2), "={r3}"(r3)
+                 : "{r0}"(major), "{r1}"(minor), "{r2}"(cb), "{r3}"(ud)
+                 : "memory"
+                 : "volatile");
+    (r0, r1, r2, r3)
+}
+
+#[inline(always)]
+// Justification: documentation is generated from mocks
+#[allow(clippy::missing_safety_doc)]
+pub unsafe fn command(
+    major: usize,
+    minor: usize,
+    arg1: usize,
+    arg2: usize,
+) -> (usize, usize, usize, usize) {
+    let (r0, r1, r2, r3);
+    asm!("svc 2" : "={r0}"(r0), "={r1}"(r1), "={r2}"(r2), "={r3}"(r3)
+                 : "{r0}"(major), "{r1}"(minor), "{r2}"(arg1), "{r3}"(arg2)
+                 : "memory"
+                 : "volatile");
+    (r0, r1, r2, r3)
+}
+
+#[inline(always)]
+// Justification: documentation is generated from mocks
+#[allow(clippy::missing_safety_doc)]
+pub unsafe fn command1(major: usize, minor: usize, arg: usize) -> (usize, usize, usize, usize) {
+    let (r0, r1, r2, r3);
+    asm!("svc 2" : "={r0}"(r0), "={r1}"(r1), "={r2}"(r2), "={r3}"(r3)
+                 : "{r0}"(major), "{r1}"(minor), "{r2}"(arg)
                  : "memory"
                  : "volatile");
-    res
+    (r0, r1, r2, r3)
 }
 
 #[inline(always)]
 // Justification: documentation is generated from mocks
 #[allow(clippy::missing_safety_doc)]
-pub unsafe fn command(major: usize, minor: usize, arg1: usize, arg2: usize) -> isize {
-    let res;
-    asm!("svc 2" : "={r0}"(res)
-                     : "{r0}"(major) "{r1}"(minor) "{r2}"(arg1) "{r3}"(arg2)
-                     : "memory"
-                     : "volatile");
-    res
+pub unsafe fn allow(
+    major: usize,
+    minor: usize,
+    slice: *mut u8,
+    len: usize,
+) -> (usize, usize, usize, usize) {
+    let (r0, r1, r2, r3);
+    asm!("svc 3" : "={r0}"(r0), "={r1}"(r1), "={r2}"(r2), "={r3}"(r3)
+                 : "{r0}"(major), "{r1}"(minor), "{r2}"(slice), "{r3}"(len)
+                 : "memory"
+                 : "volatile");
+    (r0, r1, r2, r3)
 }
 
 #[inline(always)]
 // Justification: documentation is generated from mocks
 #[allow(clippy::missing_safety_doc)]
-pub unsafe fn command1(major: usize, minor: usize, arg: usize) -> isize {
-    let res;
-    asm!("svc 2" : "={r0}"(res)
-                 : "{r0}"(major) "{r1}"(minor) "{r2}"(arg)
+pub unsafe fn allow_readonly(
+    major: usize,
+    minor: usize,
+    slice: *const u8,
+    len: usize,
+) -> (usize, usize, usize, usize) {
+    let (r0, r1, r2, r3);
+    asm!("svc 4" : "={r0}"(r0), "={r1}"(r1), "={r2}"(r2), "={r3}"(r3)
+                 : "{r0}"(major), "{r1}"(minor), "{r2}"(slice), "{r3}"(len)
                  : "memory"
                  : "volatile");
-    res
+    (r0, r1, r2, r3)
 }
 
 #[inline(always)]
 // Justification: documentation is generated from mocks
 #[allow(clippy::missing_safety_doc)]
-pub unsafe fn allow(major: usize, minor: usize, slice: *mut u8, len: usize) -> isize {
-    let res;
-    asm!("svc 3" : "={r0}"(res)
-                 : "{r0}"(major) "{r1}"(minor) "{r2}"(slice) "{r3}"(len)
+pub unsafe fn memop(major: u32, arg1: usize) -> (usize, usize, usize, usize) {
+    let (r0, r1, r2, r3);
+    asm!("svc 5" : "={r0}"(r0), "={r1}"(r1), "={r2}"(r2), "={r3}"(r3)
+                 : "{r0}"(major), "{r1}"(arg1)
                  : "memory"
                  : "volatile");
-    res
+    (r0, r1, r2, r3)
 }
 
 #[inline(always)]
 // Justification: documentation is generated from mocks
 #[allow(clippy::missing_safety_doc)]
-pub unsafe fn memop(major: u32, arg1: usize) -> isize {
-    let res;
-    asm!("svc 4" : "={r0}"(res)
-                 : "{r0}"(major) "{r1}"(arg1)
+pub unsafe fn exit(exit_number: usize, completion_code: usize) -> (usize, usize, usize, usize) {
+    let (r0, r1, r2, r3);
+    asm!("svc 6" : "={r0}"(r0), "={r1}"(r1), "={r2}"(r2), "={r3}"(r3)
+                 : "{r0}"(exit_number), "{r1}"(completion_code)
                  : "memory"
                  : "volatile");
-    res
+    (r0, r1, r2, r3)
 }
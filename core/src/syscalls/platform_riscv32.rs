@@ -1,10 +1,14 @@
+// The system call class is passed in a4, the arguments in a0-a3. The kernel
+// returns the return variant and its values in a0-a3.
+
 #[inline(always)]
 // Justification: documentation is generated from mocks
 #[allow(clippy::missing_safety_doc)]
 pub unsafe fn yieldk() {
     /* TODO: Stop yielding */
     asm! (
-            "li    a0, 0
+            "li    a4, 0
+            li    a0, 1
             ecall"
             :
             :
@@ -13,6 +17,26 @@ pub unsafe fn yieldk() {
             : "volatile");
 }
 
+#[inline(always)]
+// Justification: documentation is generated from mocks
+#[allow(clippy::missing_safety_doc)]
+pub unsafe fn yield_no_wait() -> bool {
+    // The kernel stores whether a callback has been executed at the address
+    // passed in a1.
+    let mut upcall_invoked: u8 = 0;
+    let _a1: usize;
+    asm! (
+            "li    a4, 0
+            li    a0, 0
+            ecall"
+            : "={x11}" (_a1)
+            : "{x11}" (&mut upcall_invoked as *mut u8)
+            : "memory", "x10", "x12", "x13", "x14", "x15", "x16", "x17",
+            "x5", "x6", "x7", "x28", "x29", "x30", "x31", "x1"
+            : "volatile");
+    upcall_invoked != 0
+}
+
 #[inline(always)]
 // Justification: documentation is generated from mocks
 #[allow(clippy::missing_safety_doc)]
@@ -21,69 +45,112 @@ pub unsafe fn subscribe(
     minor: usize,
     cb: *const unsafe extern "C" fn(usize, usize, usize, usize),
     ud: usize,
-) -> isize {
-    let res;
-    asm!("li    a0, 1
+) -> (usize, usize, usize, usize) {
+    let (a0, a1, a2, a3);
+    asm!("li    a4, 1
           ecall"
-         : "={x10}" (res)
-         : "{x11}" (major), "{x12}" (minor), "{x13}" (cb), "{x14}" (ud)
-         : "memory"
-         : "volatile" );
-    res
+         : "={x10}" (a0), "={x11}" (a1), "={x12}" (a2), "={x13}" (a3)
+         : "{x10}" (major), "{x11}" (minor), "{x12}" (cb), "{x13}" (ud)
+         : "memory", "x14"
+         : "volatile");
+    (a0, a1, a2, a3)
 }
 
 #[inline(always)]
 // Justification: documentation is generated from mocks
 #[allow(clippy::missing_safety_doc)]
-pub unsafe fn command(major: usize, minor: usize, arg1: usize, arg2: usize) -> isize {
-    let res;
-    asm!("li    a0, 2
+pub unsafe fn command(
+    major: usize,
+    minor: usize,
+    arg1: usize,
+    arg2: usize,
+) -> (usize, usize, usize, usize) {
+    let (a0, a1, a2, a3);
+    asm!("li    a4, 2
           ecall"
-         : "={x10}" (res)
-         : "{x11}" (major), "{x12}" (minor), "{x13}" (arg1), "{x14}" (arg2)
-         : "memory"
+         : "={x10}" (a0), "={x11}" (a1), "={x12}" (a2), "={x13}" (a3)
+         : "{x10}" (major), "{x11}" (minor), "{x12}" (arg1), "{x13}" (arg2)
+         : "memory", "x14"
          : "volatile");
-    res
+    (a0, a1, a2, a3)
 }
 
 #[inline(always)]
 // Justification: documentation is generated from mocks
 #[allow(clippy::missing_safety_doc)]
-pub unsafe fn command1(major: usize, minor: usize, arg: usize) -> isize {
-    let res;
-    asm!("li    a0, 2
+pub unsafe fn command1(major: usize, minor: usize, arg: usize) -> (usize, usize, usize, usize) {
+    let (a0, a1, a2, a3);
+    asm!("li    a4, 2
+          ecall"
+         : "={x10}" (a0), "={x11}" (a1), "={x12}" (a2), "={x13}" (a3)
+         : "{x10}" (major), "{x11}" (minor), "{x12}" (arg)
+         : "memory", "x14"
+         : "volatile");
+    (a0, a1, a2, a3)
+}
+
+#[inline(always)]
+// Justification: documentation is generated from mocks
+#[allow(clippy::missing_safety_doc)]
+pub unsafe fn allow(
+    major: usize,
+    minor: usize,
+    slice: *mut u8,
+    len: usize,
+) -> (usize, usize, usize, usize) {
+    let (a0, a1, a2, a3);
+    asm!("li    a4, 3
+          ecall"
+         : "={x10}" (a0), "={x11}" (a1), "={x12}" (a2), "={x13}" (a3)
+         : "{x10}" (major), "{x11}" (minor), "{x12}" (slice), "{x13}" (len)
+         : "memory", "x14"
+         : "volatile");
+    (a0, a1, a2, a3)
+}
+
+#[inline(always)]
+// Justification: documentation is generated from mocks
+#[allow(clippy::missing_safety_doc)]
+pub unsafe fn allow_readonly(
+    major: usize,
+    minor: usize,
+    slice: *const u8,
+    len: usize,
+) -> (usize, usize, usize, usize) {
+    let (a0, a1, a2, a3);
+    asm!("li    a4, 4
           ecall"
-         : "={x10}" (res)
-         : "{x11}" (major), "{x12}" (minor), "{x13}" (arg)
-         : "memory"
+         : "={x10}" (a0), "={x11}" (a1), "={x12}" (a2), "={x13}" (a3)
+         : "{x10}" (major), "{x11}" (minor), "{x12}" (slice), "{x13}" (len)
+         : "memory", "x14"
          : "volatile");
-    res
+    (a0, a1, a2, a3)
 }
 
 #[inline(always)]
 // Justification: documentation is generated from mocks
 #[allow(clippy::missing_safety_doc)]
-pub unsafe fn allow(major: usize, minor: usize, slice: *mut u8, len: usize) -> isize {
-    let res;
-    asm!("li    a0, 3
+pub unsafe fn memop(major: u32, arg1: usize) -> (usize, usize, usize, usize) {
+    let (a0, a1, a2, a3);
+    asm!("li    a4, 5
           ecall"
-         : "={x10}" (res)
-         : "{x11}" (major), "{x12}" (minor), "{x13}" (slice), "{x14}" (len)
-         : "memory"
+         : "={x10}" (a0), "={x11}" (a1), "={x12}" (a2), "={x13}" (a3)
+         : "{x10}" (major), "{x11}" (arg1)
+         : "memory", "x14"
          : "volatile");
-    res
+    (a0, a1, a2, a3)
 }
 
 #[inline(always)]
 // Justification: documentation is generated from mocks
 #[allow(clippy::missing_safety_doc)]
-pub unsafe fn memop(major: u32, arg1: usize) -> isize {
-    let res;
-    asm!("li    a0, 4
+pub unsafe fn exit(exit_number: usize, completion_code: usize) -> (usize, usize, usize, usize) {
+    let (a0, a1, a2, a3);
+    asm!("li    a4, 6
           ecall"
-         : "={x10}" (res)
-         : "{x11}" (major), "{x12}" (arg1)
-         : "memory"
+         : "={x10}" (a0), "={x11}" (a1), "={x12}" (a2), "={x13}" (a3)
+         : "{x10}" (exit_number), "{x11}" (completion_code)
+         : "memory", "x14"
          : "volatile");
-    res
+    (a0, a1, a2, a3)
 }
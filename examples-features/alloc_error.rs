@@ -25,7 +25,5 @@ unsafe fn alloc_error_handler(_: Layout) -> ! {
         let mut console = drivers.console.create_console();
         let _ = writeln!(console, "alloc_error_handler called");
     }
-    loop {
-        syscalls::raw::yieldk();
-    }
+    syscalls::exit(1)
 }
@@ -0,0 +1,10 @@
+// Panics with a message which is printed to the console by the `console_panic_handler`.
+
+#![no_std]
+
+use libtock::result::TockResult;
+
+#[libtock::main]
+async fn main() -> TockResult<()> {
+    panic!("Bye world!");
+}
@@ -0,0 +1,41 @@
+// Blinks P0.03 and prints samples of ADC channel 0 using the traits of the `embedded-hal` crate,
+// as device drivers from crates.io do.
+
+#![no_std]
+
+use core::fmt::Write;
+use embedded_hal::adc::OneShot;
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::digital::v2::ToggleableOutputPin;
+use libtock::adc::channel::Channel0;
+use libtock::result::TockResult;
+
+#[libtock::main]
+async fn main() -> TockResult<()> {
+    let mut drivers = libtock::retrieve_drivers()?;
+
+    let mut gpio_driver = drivers.gpio.init_driver()?;
+    let mut adc_driver = drivers.adc.init_driver()?;
+    let mut adc = adc_driver.one_shot();
+    let mut timer_driver = drivers.timer.create_timer_driver();
+    let mut delay = timer_driver.activate()?;
+    let mut console = drivers.console.create_console();
+
+    let mut gpio = gpio_driver.gpios().next().unwrap();
+    let mut pin = gpio.enable_output()?;
+    let mut channel = Channel0;
+    loop {
+        blink(&mut pin, &mut delay)?;
+        let value: u16 = nb::block!(adc.read(&mut channel))?;
+        writeln!(console, "value: {}", value)?;
+    }
+}
+
+fn blink<P: ToggleableOutputPin, D: DelayMs<u16>>(
+    pin: &mut P,
+    delay: &mut D,
+) -> Result<(), P::Error> {
+    pin.toggle()?;
+    delay.delay_ms(500);
+    Ok(())
+}
@@ -0,0 +1,21 @@
+// Logs messages using the `log` crate, which are printed to the console.
+
+#![no_std]
+
+use libtock::result::TockResult;
+use libtock::timer::Duration;
+
+#[libtock::main(log = "info")]
+async fn main() -> TockResult<()> {
+    let mut drivers = libtock::retrieve_drivers()?;
+    let mut timer_driver = drivers.timer.create_timer_driver();
+    let timer_driver = timer_driver.activate()?;
+
+    let mut ticks = 0;
+    loop {
+        log::info!("Tick {}", ticks);
+        log::debug!("Debug messages are not printed");
+        ticks += 1;
+        timer_driver.sleep(Duration::from_ms(1000)).await?;
+    }
+}
@@ -18,7 +18,5 @@ unsafe fn panic_handler(_info: &PanicInfo) -> ! {
         let mut console = drivers.console.create_console();
         let _ = writeln!(console, "panic_handler called");
     }
-    loop {
-        syscalls::raw::yieldk();
-    }
+    syscalls::exit(1)
 }
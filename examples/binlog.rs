@@ -0,0 +1,25 @@
+// This example logs a counter in the compact binary format. Decode the console output with
+// `cargo run -p binlog-decoder -- <path to the ELF file of this example>`.
+
+#![no_std]
+
+use libtock::binlog;
+use libtock::result::TockResult;
+use libtock::timer::Duration;
+
+#[libtock::main]
+async fn main() -> TockResult<()> {
+    let mut drivers = libtock::retrieve_drivers()?;
+    let mut timer_driver = drivers.timer.create_timer_driver();
+    let timer_driver = timer_driver.activate()?;
+
+    binlog::info!("binlog example started");
+    for counter in 0u32.. {
+        binlog::debug!("counter: {=u32} ({=u32:x})", counter, counter);
+        if counter % 10 == 0 {
+            binlog::warn!("{=u32} is a multiple of {=u8}", counter, 10);
+        }
+        timer_driver.sleep(Duration::from_ms(500)).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,26 @@
+// Scans the I2C bus and prints the addresses of the devices which acknowledge a write.
+
+#![no_std]
+
+use core::fmt::Write;
+use libtock::result::OtherError;
+use libtock::result::TockError;
+use libtock::result::TockResult;
+
+#[libtock::main]
+async fn main() -> TockResult<()> {
+    let mut drivers = libtock::retrieve_drivers()?;
+    let mut console = drivers.console.create_console();
+    let mut i2c = drivers.i2c.init_driver()?;
+
+    // Addresses 0x00-0x07 and 0x78-0x7f are reserved.
+    for address in 0x08..0x78 {
+        match i2c.write(address, &[]).await {
+            Ok(()) => writeln!(console, "Found device at {:#04x}", address)?,
+            Err(TockError::Other(OtherError::I2cDriverNak)) => {}
+            Err(error) => return Err(error),
+        }
+    }
+    writeln!(console, "Scan complete")?;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+// This example provides a shell on the console to switch the LEDs on and off, e.g. `led 0 on`.
+
+#![no_std]
+
+use core::fmt::Write;
+use libtock::console::Console;
+use libtock::result::OtherError;
+use libtock::result::TockResult;
+use libtock::shell::Shell;
+
+#[libtock::main]
+async fn main() -> TockResult<()> {
+    let mut drivers = libtock::retrieve_drivers()?;
+    let leds_driver = drivers.leds.init_driver()?;
+
+    let led = |_: &mut Console, args: &[&str]| {
+        let led_num = match args.first().and_then(|arg| arg.parse().ok()) {
+            Some(led_num) => led_num,
+            None => return Err(OtherError::OutOfRange.into()),
+        };
+        let led = leds_driver.get(led_num)?;
+        match args.get(1) {
+            Some(&"on") => led.on(),
+            Some(&"off") => led.off(),
+            _ => led.toggle(),
+        }
+    };
+    let leds = |console: &mut Console, _: &[&str]| {
+        write!(console, "{} LEDs\r\n", leds_driver.num_leds())?;
+        Ok(())
+    };
+
+    let mut shell = Shell::new(drivers.console.create_console(), "tock> ");
+    shell.register("led", "led <n> [on|off]: switches or toggles an LED", &led)?;
+    shell.register("leds", "Prints the number of LEDs", &leds)?;
+    shell.run().await
+}
@@ -0,0 +1,34 @@
+// Reads the JEDEC ID of an external flash connected to chip select 0.
+
+#![no_std]
+
+use core::fmt::Write;
+use libtock::result::TockResult;
+use libtock::spi::Phase;
+use libtock::spi::Polarity;
+
+const READ_JEDEC_ID: u8 = 0x9f;
+
+#[libtock::main]
+async fn main() -> TockResult<()> {
+    let mut drivers = libtock::retrieve_drivers()?;
+    let mut console = drivers.console.create_console();
+    let mut spi = drivers.spi.init_driver()?;
+
+    spi.select_chip(0)?;
+    spi.set_polarity(Polarity::IdleLow)?;
+    spi.set_phase(Phase::SampleLeading)?;
+    spi.set_rate(1_000_000)?;
+    writeln!(console, "SPI rate: {} Hz", spi.rate()?)?;
+
+    // The ID is received while the dummy bytes after the command are sent.
+    let mut received = [0; 4];
+    spi.read_write(&[READ_JEDEC_ID, 0, 0, 0], &mut received)
+        .await?;
+    writeln!(
+        console,
+        "Manufacturer: {:#04x}, device: {:#04x}{:02x}",
+        received[1], received[2], received[3]
+    )?;
+    Ok(())
+}
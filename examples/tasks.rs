@@ -0,0 +1,56 @@
+#![no_std]
+
+use futures::pin_mut;
+use libtock::executor::Executor;
+use libtock::executor::Task;
+use libtock::leds::Led;
+use libtock::result::TockResult;
+use libtock::timer::Duration;
+use libtock::timer::ParallelSleepDriver;
+
+async fn blink(
+    timer_driver: &ParallelSleepDriver<'_>,
+    duration: Duration<usize>,
+    led: Led<'_>,
+) -> TockResult<()> {
+    loop {
+        led.toggle()?;
+
+        timer_driver.sleep(duration).await?;
+    }
+}
+
+#[libtock::main]
+async fn main() -> TockResult<()> {
+    let mut drivers = libtock::retrieve_drivers()?;
+
+    let leds_driver = drivers.leds.init_driver()?;
+    let mut timer_driver = drivers.timer.create_timer_driver();
+    let timer_driver = timer_driver.activate()?;
+
+    let mut leds = leds_driver.leds();
+
+    let task_1 = Task::new(blink(
+        &timer_driver,
+        Duration::from_ms(500),
+        leds.next().unwrap(),
+    ));
+    let task_2 = Task::new(blink(
+        &timer_driver,
+        Duration::from_ms(333),
+        leds.next().unwrap(),
+    ));
+    pin_mut!(task_1);
+    pin_mut!(task_2);
+
+    let executor = Executor::new();
+    let join_handle_1 = executor.spawn(task_1.as_ref())?;
+    executor.spawn(task_2.as_ref())?;
+
+    // The tasks keep blinking while main blinks the third LED.
+    let led_3 = leds.next().unwrap();
+    executor
+        .run_until(blink(&timer_driver, Duration::from_ms(250), led_3))
+        .await?;
+    executor.run_until(join_handle_1).await
+}
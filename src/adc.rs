@@ -1,9 +1,19 @@
 use crate::callback::CallbackSubscription;
 use crate::callback::Consumer;
+#[cfg(feature = "embedded-hal")]
+use crate::executor;
+#[cfg(feature = "embedded-hal")]
+use crate::futures;
+#[cfg(feature = "embedded-hal")]
+use crate::result::TockError;
 use crate::result::TockResult;
 use crate::shared_memory::SharedMemory;
 use crate::syscalls;
+#[cfg(feature = "embedded-hal")]
+use core::cell::Cell;
 use core::marker::PhantomData;
+#[cfg(feature = "embedded-hal")]
+use core::mem;
 
 pub const DRIVER_NUMBER: usize = 0x0005;
 pub const BUFFER_SIZE: usize = 128;
@@ -33,7 +43,7 @@ impl AdcDriverFactory {
     pub fn init_driver(&mut self) -> TockResult<Adc> {
         let adc = Adc {
             // num_channels
-            num_channels: syscalls::command(DRIVER_NUMBER, command_nr::COUNT, 0, 0)?,
+            num_channels: syscalls::command_u32(DRIVER_NUMBER, command_nr::COUNT, 0, 0)? as usize,
             lifetime: PhantomData,
         };
         Ok(adc)
@@ -143,3 +153,137 @@ impl<'a> Drop for Adc<'a> {
         let _ = self.stop();
     }
 }
+
+#[cfg(feature = "embedded-hal")]
+impl<'a> Adc<'a> {
+    /// Returns a handle reading single samples via `embedded_hal::adc::OneShot`. The handle
+    /// subscribes its own callback for every read, so it borrows the ADC mutably and cannot be
+    /// created while a callback subscribed via [Adc::subscribe] is active.
+    pub fn one_shot(&mut self) -> AdcOneShot<'_, 'a> {
+        AdcOneShot { adc: self }
+    }
+}
+
+/// Reads single samples via `embedded_hal::adc::OneShot`, see [Adc::one_shot].
+#[cfg(feature = "embedded-hal")]
+pub struct AdcOneShot<'b, 'a> {
+    adc: &'b mut Adc<'a>,
+}
+
+/// Samples the channel once and blocks until the sample is available.
+#[cfg(feature = "embedded-hal")]
+impl<'b, 'a, C: embedded_hal::adc::Channel<Adc<'a>, ID = usize>>
+    embedded_hal::adc::OneShot<Adc<'a>, u16, C> for AdcOneShot<'b, 'a>
+{
+    type Error = TockError;
+
+    fn read(&mut self, _channel: &mut C) -> nb::Result<u16, TockError> {
+        self.sample_blocking(C::channel()).map_err(nb::Error::Other)
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<'b, 'a> AdcOneShot<'b, 'a> {
+    fn sample_blocking(&self, channel: usize) -> TockResult<u16> {
+        let sample = Cell::new(None);
+        let mut sample_done = |sampled_channel, value| {
+            if sampled_channel == channel {
+                sample.set(Some(value as u16));
+            }
+        };
+        let subscription = syscalls::subscribe::<AdcEventConsumer, _>(
+            DRIVER_NUMBER,
+            subscribe_nr::SUBSCRIBE_CALLBACK,
+            &mut sample_done,
+        )?;
+        self.adc.sample(channel)?;
+        let value = unsafe {
+            executor::block_on(futures::wait_for_value_on_upcall(
+                DRIVER_NUMBER,
+                subscribe_nr::SUBSCRIBE_CALLBACK,
+                || sample.get(),
+            ))
+        };
+        mem::drop(subscription);
+        Ok(value)
+    }
+}
+
+/// Channels of the [Adc] to be read via `embedded_hal::adc::OneShot`. `embedded-hal` identifies
+/// channels by their type, so there is one type per channel.
+#[cfg(feature = "embedded-hal")]
+pub mod channel {
+    use super::Adc;
+
+    macro_rules! channels {
+        ($($name:ident = $channel:expr,)*) => {
+            $(
+                pub struct $name;
+
+                impl<'a> embedded_hal::adc::Channel<Adc<'a>> for $name {
+                    type ID = usize;
+
+                    fn channel() -> usize {
+                        $channel
+                    }
+                }
+            )*
+        };
+    }
+
+    channels! {
+        Channel0 = 0,
+        Channel1 = 1,
+        Channel2 = 2,
+        Channel3 = 3,
+        Channel4 = 4,
+        Channel5 = 5,
+        Channel6 = 6,
+        Channel7 = 7,
+        Channel8 = 8,
+        Channel9 = 9,
+        Channel10 = 10,
+        Channel11 = 11,
+        Channel12 = 12,
+        Channel13 = 13,
+        Channel14 = 14,
+        Channel15 = 15,
+    }
+}
+
+#[cfg(all(test, feature = "embedded-hal"))]
+mod test {
+    use super::channel::Channel2;
+    use super::*;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use crate::syscalls::CommandReturn;
+    use embedded_hal::adc::OneShot;
+
+    #[test]
+    pub fn one_shot_reads_a_single_sample_of_the_channel() {
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(
+            DRIVER_NUMBER,
+            |handle: &mut DriverHandle, command_number, channel, _| {
+                match command_number {
+                    command_nr::COUNT => return CommandReturn::SuccessU32(4),
+                    command_nr::START => {
+                        handle.schedule_upcall(subscribe_nr::SUBSCRIBE_CALLBACK, (0, channel, 1234))
+                    }
+                    _ => {}
+                }
+                CommandReturn::Success
+            },
+        );
+
+        let sample = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut adc = drivers.adc.init_driver().unwrap();
+            let mut adc = adc.one_shot();
+            adc.read(&mut Channel2).unwrap()
+        });
+
+        assert_eq!(sample, 1234);
+    }
+}
@@ -0,0 +1,228 @@
+//! Compact binary logging over the console. Instead of formatting the text on the device, log
+//! statements send the index of their format string followed by the raw argument bytes. The
+//! format strings are interned at compile time in the `.binlog` section of the ELF file, which is
+//! not loaded onto the device. Decode the output on the host using
+//! `cargo run -p binlog-decoder -- <elf> [captured output]`.
+//!
+//! Arguments are declared with placeholders `{=<type>}` where `<type>` is one of `u8`, `u16`,
+//! `u32`, `u64`, `usize`, `i8`, `i16`, `i32`, `i64`, `isize`, `bool`, `str` and `[u8]`. Integers
+//! can be printed in hexadecimal or binary using `{=u32:x}` or `{=u32:b}`. Braces are escaped as
+//! `{{` and `}}`.
+//!
+//! ```no_run
+//! # let temperature = 23;
+//! libtock::binlog::info!("temperature: {=i32} °C", temperature);
+//! libtock::binlog::debug!("status register: {=u8:b}", 0b1010u8);
+//! ```
+//!
+//! Statements are written to the same console as text output, so text printed with `println!`
+//! shows up as malformed records in the decoder. Records that are longer than [MAX_FRAME_LEN]
+//! bytes or are logged while the console is in use, e.g. from an upcall, are dropped.
+//!
+//! Every record is encoded as the [COBS](https://en.wikipedia.org/wiki/Consistent_Overhead_Byte_Stuffing)
+//! encoding of the index and the arguments terminated by a zero byte. The index and `usize`
+//! arguments are LEB128-encoded, `isize` arguments are zigzag- and LEB128-encoded, the other
+//! integers are little-endian. `bool` takes a single byte. `str` and `[u8]` are prefixed by their
+//! LEB128-encoded length.
+
+use crate::console;
+
+pub use libtock_codegen::binlog_debug as debug;
+pub use libtock_codegen::binlog_error as error;
+pub use libtock_codegen::binlog_info as info;
+pub use libtock_codegen::binlog_trace as trace;
+pub use libtock_codegen::binlog_warn as warn;
+
+/// Maximum length of a record before COBS encoding.
+pub const MAX_FRAME_LEN: usize = 128;
+
+const MAX_ENCODED_LEN: usize = MAX_FRAME_LEN + MAX_FRAME_LEN / 254 + 2;
+
+/// Encodes a single record. Used by the code generated for the log statements.
+#[doc(hidden)]
+pub struct Encoder {
+    frame: [u8; MAX_FRAME_LEN],
+    len: usize,
+    overflowed: bool,
+}
+
+impl Encoder {
+    pub fn new(index: usize) -> Encoder {
+        let mut encoder = Encoder {
+            frame: [0; MAX_FRAME_LEN],
+            len: 0,
+            overflowed: false,
+        };
+        encoder.encode_usize(index);
+        encoder
+    }
+
+    pub fn encode_u8(&mut self, value: u8) {
+        self.push(&[value]);
+    }
+
+    pub fn encode_u16(&mut self, value: u16) {
+        self.push(&value.to_le_bytes());
+    }
+
+    pub fn encode_u32(&mut self, value: u32) {
+        self.push(&value.to_le_bytes());
+    }
+
+    pub fn encode_u64(&mut self, value: u64) {
+        self.push(&value.to_le_bytes());
+    }
+
+    pub fn encode_usize(&mut self, mut value: usize) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.push(&[byte]);
+                return;
+            }
+            self.push(&[byte | 0x80]);
+        }
+    }
+
+    pub fn encode_i8(&mut self, value: i8) {
+        self.push(&value.to_le_bytes());
+    }
+
+    pub fn encode_i16(&mut self, value: i16) {
+        self.push(&value.to_le_bytes());
+    }
+
+    pub fn encode_i32(&mut self, value: i32) {
+        self.push(&value.to_le_bytes());
+    }
+
+    pub fn encode_i64(&mut self, value: i64) {
+        self.push(&value.to_le_bytes());
+    }
+
+    pub fn encode_isize(&mut self, value: isize) {
+        let bits = 8 * core::mem::size_of::<isize>() - 1;
+        self.encode_usize(((value << 1) ^ (value >> bits)) as usize);
+    }
+
+    pub fn encode_bool(&mut self, value: bool) {
+        self.push(&[value as u8]);
+    }
+
+    pub fn encode_str(&mut self, value: &str) {
+        self.encode_bytes(value.as_bytes());
+    }
+
+    pub fn encode_bytes(&mut self, value: &[u8]) {
+        self.encode_usize(value.len());
+        self.push(value);
+    }
+
+    /// Writes the record to the console unless it overflowed.
+    pub fn finish(self) {
+        let mut encoded = [0; MAX_ENCODED_LEN];
+        let len = match self.encode_frame(&mut encoded) {
+            Some(len) => len,
+            None => return,
+        };
+        // Records logged while the console is in use, e.g. from an upcall, are dropped.
+        let _ = console::write_to_global_console(|console| console.write(&encoded[..len]));
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        let end = self.len + bytes.len();
+        if self.overflowed || end > MAX_FRAME_LEN {
+            self.overflowed = true;
+            return;
+        }
+        self.frame[self.len..end].copy_from_slice(bytes);
+        self.len = end;
+    }
+
+    /// COBS-encodes the record and appends the terminating zero byte. Returns the length of the
+    /// encoded record.
+    fn encode_frame(&self, encoded: &mut [u8; MAX_ENCODED_LEN]) -> Option<usize> {
+        if self.overflowed {
+            return None;
+        }
+        let mut code_index = 0;
+        let mut len = 1;
+        for &byte in &self.frame[..self.len] {
+            if byte != 0 {
+                encoded[len] = byte;
+                len += 1;
+            }
+            if byte == 0 || len - code_index == 0xff {
+                encoded[code_index] = (len - code_index) as u8;
+                code_index = len;
+                len += 1;
+            }
+        }
+        encoded[code_index] = (len - code_index) as u8;
+        encoded[len] = 0;
+        Some(len + 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::console::fake::FakeConsole;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use std::vec::Vec;
+
+    fn encoded(encoder: &Encoder) -> Option<Vec<u8>> {
+        let mut encoded = [0; MAX_ENCODED_LEN];
+        let len = encoder.encode_frame(&mut encoded)?;
+        Some(encoded[..len].to_vec())
+    }
+
+    #[test]
+    pub fn arguments_are_encoded_compactly() {
+        let mut encoder = Encoder::new(300);
+        encoder.encode_u16(0x1234);
+        encoder.encode_isize(-2);
+        encoder.encode_bool(true);
+        encoder.encode_str("ab");
+        assert_eq!(
+            &encoder.frame[..encoder.len],
+            &[0xac, 0x02, 0x34, 0x12, 0x03, 0x01, 0x02, b'a', b'b']
+        );
+    }
+
+    #[test]
+    pub fn frames_do_not_contain_zero_bytes() {
+        let mut encoder = Encoder::new(1);
+        encoder.encode_u32(0x0100);
+        assert_eq!(
+            encoded(&encoder),
+            Some(vec![0x02, 0x01, 0x02, 0x01, 0x01, 0x01, 0x00])
+        );
+
+        let mut encoder = Encoder::new(1);
+        encoder.encode_bytes(&[0xff; 125]);
+        let encoded = encoded(&encoder).unwrap();
+        assert_eq!(encoded.len(), 127 + 2);
+        assert_eq!(encoded.iter().position(|&byte| byte == 0), Some(128));
+    }
+
+    #[test]
+    pub fn overflowing_records_are_dropped() {
+        let fake_console = FakeConsole::default();
+        let output = fake_console.output.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(console::fake::DRIVER_NUMBER, fake_console);
+
+        kernel.run(|| {
+            let mut encoder = Encoder::new(7);
+            encoder.encode_bytes(&[1; MAX_FRAME_LEN]);
+            encoder.finish();
+            let mut encoder = Encoder::new(7);
+            encoder.encode_u8(5);
+            encoder.finish();
+        });
+
+        assert_eq!(*output.borrow(), vec![0x03, 0x07, 0x05, 0x00]);
+    }
+}
@@ -25,7 +25,7 @@ pub struct ButtonsDriverFactory;
 impl ButtonsDriverFactory {
     pub fn init_driver(&mut self) -> TockResult<ButtonsDriver> {
         let buttons_driver = ButtonsDriver {
-            num_buttons: syscalls::command(DRIVER_NUMBER, command_nr::COUNT, 0, 0)?,
+            num_buttons: syscalls::command_u32(DRIVER_NUMBER, command_nr::COUNT, 0, 0)? as usize,
             lifetime: PhantomData,
         };
         Ok(buttons_driver)
@@ -137,7 +137,8 @@ impl<'a> Button<'a> {
     }
 
     pub fn read(&self) -> TockResult<ButtonState> {
-        let button_state = syscalls::command(DRIVER_NUMBER, command_nr::READ, self.button_num, 0)?;
+        let button_state =
+            syscalls::command_u32(DRIVER_NUMBER, command_nr::READ, self.button_num, 0)?;
         match button_state {
             0 => Ok(ButtonState::Released),
             1 => Ok(ButtonState::Pressed),
@@ -165,3 +166,50 @@ impl<'a> Button<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use crate::syscalls::CommandReturn;
+    use core::cell::RefCell;
+
+    #[test]
+    pub fn button_events_are_passed_to_the_callback() {
+        let mut kernel = FakeKernel::new();
+        kernel
+            .add_driver(
+                DRIVER_NUMBER,
+                |_: &mut DriverHandle, command_number, _, _| match command_number {
+                    command_nr::COUNT => CommandReturn::SuccessU32(2),
+                    _ => CommandReturn::Success,
+                },
+            )
+            .schedule_upcall(DRIVER_NUMBER, subscribe_nr::SUBSCRIBE_CALLBACK, (1, 1, 0))
+            .schedule_upcall(DRIVER_NUMBER, subscribe_nr::SUBSCRIBE_CALLBACK, (1, 0, 0));
+
+        let events = kernel
+            .run(|| -> TockResult<_> {
+                let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+                let buttons_driver = drivers.buttons.init_driver()?;
+                let events = RefCell::new(Vec::new());
+                let mut callback =
+                    |button_num, state| events.borrow_mut().push((button_num, state));
+                let subscription = buttons_driver.subscribe(&mut callback)?;
+                buttons_driver.get(1)?.enable_interrupt()?;
+                unsafe {
+                    syscalls::raw::yieldk();
+                    syscalls::raw::yieldk();
+                }
+                core::mem::drop(subscription);
+                Ok(events.into_inner())
+            })
+            .unwrap();
+
+        assert_eq!(
+            events,
+            vec![(1, ButtonState::Pressed), (1, ButtonState::Released)]
+        );
+    }
+}
@@ -1,6 +1,10 @@
-use crate::callback::Identity0Consumer;
+use crate::callback::Identity1Consumer;
+use crate::callback::Identity2Consumer;
 use crate::executor;
 use crate::futures;
+use crate::result::CommandError;
+use crate::result::ErrorCode;
+use crate::result::OtherError;
 use crate::result::TockResult;
 use crate::syscalls;
 use core::cell::Cell;
@@ -11,68 +15,242 @@ const DRIVER_NUMBER: usize = 1;
 
 mod command_nr {
     pub const WRITE: usize = 1;
+    pub const READ: usize = 2;
+    pub const ABORT_READ: usize = 3;
 }
 
 mod subscribe_nr {
     pub const SET_ALARM: usize = 1;
+    pub const READ_DONE: usize = 2;
 }
 
 mod allow_nr {
     pub const SHARE_BUFFER: usize = 1;
+    pub const READ_BUFFER: usize = 1;
 }
 
 #[non_exhaustive]
 pub struct ConsoleDriver;
 
+/// The maximum number of bytes requested by a single read of [read_line](Console::read_line).
+/// Bytes read past the end of the line are kept for the following reads.
+const READ_AHEAD_SIZE: usize = 16;
+
 impl ConsoleDriver {
     pub fn create_console(self) -> Console {
-        Console {
-            allow_buffer: [0; 64],
-        }
+        Console::new()
     }
 }
 
 pub struct Console {
-    allow_buffer: [u8; 64],
+    read_ahead: [u8; READ_AHEAD_SIZE],
+    read_ahead_len: usize,
+    /// Set while the console is used by [write_to_global_console].
+    in_use: Option<ConsoleInUse>,
 }
 
 impl Console {
+    fn new() -> Console {
+        Console {
+            read_ahead: [0; READ_AHEAD_SIZE],
+            read_ahead_len: 0,
+            in_use: None,
+        }
+    }
+
+    /// Write the text to the console. The text is shared read-only with the kernel, so neither
+    /// copying nor a buffer in RAM is required. Fails if the kernel does not write any of the
+    /// remaining bytes, and with `OtherError::ConsoleDriverBusy` if another write is in progress,
+    /// e.g. when writing from an upcall delivered while a write is waiting for the kernel.
     pub fn write<S: AsRef<[u8]>>(&mut self, text: S) -> TockResult<()> {
+        let _in_use = match self.in_use {
+            Some(_) => None,
+            None => Some(ConsoleInUse::mark()?),
+        };
         let mut not_written_yet = text.as_ref();
         while !not_written_yet.is_empty() {
-            let num_bytes_to_print = self.allow_buffer.len().min(not_written_yet.len());
-            self.allow_buffer[..num_bytes_to_print]
-                .copy_from_slice(&not_written_yet[..num_bytes_to_print]);
-            self.flush(num_bytes_to_print)?;
-            not_written_yet = &not_written_yet[num_bytes_to_print..];
+            let num_bytes_written = self.flush(not_written_yet)?;
+            if num_bytes_written == 0 {
+                return Err(CommandError {
+                    driver_number: DRIVER_NUMBER,
+                    command_number: command_nr::WRITE,
+                    arg1: not_written_yet.len(),
+                    arg2: 0,
+                    error_code: ErrorCode::Fail,
+                }
+                .into());
+            }
+            not_written_yet = &not_written_yet[num_bytes_written.min(not_written_yet.len())..];
         }
         Ok(())
     }
 
-    fn flush(&mut self, num_bytes_to_print: usize) -> TockResult<()> {
-        let shared_memory = syscalls::allow(
+    fn flush(&mut self, text: &[u8]) -> TockResult<usize> {
+        let shared_memory = syscalls::allow_readonly(DRIVER_NUMBER, allow_nr::SHARE_BUFFER, text)?;
+
+        let num_bytes_written = Cell::new(None);
+        let mut written_alarm = |num_bytes| num_bytes_written.set(Some(num_bytes));
+        let subscription = syscalls::subscribe::<Identity1Consumer, _>(
             DRIVER_NUMBER,
-            allow_nr::SHARE_BUFFER,
-            &mut self.allow_buffer[..num_bytes_to_print],
+            subscribe_nr::SET_ALARM,
+            &mut written_alarm,
         )?;
 
-        let is_written = Cell::new(false);
-        let mut is_written_alarm = || is_written.set(true);
-        let subscription = syscalls::subscribe::<Identity0Consumer, _>(
+        syscalls::command(DRIVER_NUMBER, command_nr::WRITE, text.len(), 0)?;
+
+        let num_bytes_written = unsafe {
+            executor::block_on(futures::wait_for_value_on_upcall(
+                DRIVER_NUMBER,
+                subscribe_nr::SET_ALARM,
+                || num_bytes_written.get(),
+            ))
+        };
+
+        mem::drop(subscription);
+        mem::drop(shared_memory);
+
+        Ok(num_bytes_written)
+    }
+
+    /// Read up to `buffer.len()` bytes and return the number of bytes read. The kernel completes
+    /// the read once the buffer is full, so use a buffer of the expected size. If the future is
+    /// dropped before the read has completed, the read is aborted.
+    pub async fn read(&mut self, buffer: &mut [u8]) -> TockResult<usize> {
+        let len = buffer.len();
+        if len == 0 {
+            return Ok(0);
+        }
+        if self.read_ahead_len > 0 {
+            return Ok(self.take_read_ahead(buffer));
+        }
+
+        let shared_memory = syscalls::allow(DRIVER_NUMBER, allow_nr::READ_BUFFER, buffer)?;
+
+        let read_result = Cell::new(None);
+        let mut read_done = |status, num_bytes| read_result.set(Some((status, num_bytes)));
+        let subscription = syscalls::subscribe::<Identity2Consumer, _>(
             DRIVER_NUMBER,
-            subscribe_nr::SET_ALARM,
-            &mut is_written_alarm,
+            subscribe_nr::READ_DONE,
+            &mut read_done,
         )?;
 
-        syscalls::command(DRIVER_NUMBER, command_nr::WRITE, num_bytes_to_print, 0)?;
+        syscalls::command(DRIVER_NUMBER, command_nr::READ, len, 0)?;
+        // Dropped before the subscription and the shared memory.
+        let pending_read = PendingRead;
 
-        unsafe { executor::block_on(futures::wait_until(|| is_written.get())) };
+        let (status, num_bytes) =
+            futures::wait_for_value_on_upcall(DRIVER_NUMBER, subscribe_nr::READ_DONE, || {
+                read_result.get()
+            })
+            .await;
 
+        mem::forget(pending_read);
         mem::drop(subscription);
         mem::drop(shared_memory);
 
+        if status != 0 {
+            return Err(CommandError {
+                driver_number: DRIVER_NUMBER,
+                command_number: command_nr::READ,
+                arg1: len,
+                arg2: 0,
+                error_code: ErrorCode::from_usize(status).unwrap_or(ErrorCode::BadRVal),
+            }
+            .into());
+        }
+        Ok(num_bytes.min(len))
+    }
+
+    /// Read until the buffer is full.
+    pub async fn read_exact(&mut self, buffer: &mut [u8]) -> TockResult<()> {
+        let mut num_bytes_read = 0;
+        while num_bytes_read < buffer.len() {
+            num_bytes_read += self.read_some(&mut buffer[num_bytes_read..]).await?;
+        }
         Ok(())
     }
+
+    /// Read a line terminated by `\n` or `\r\n` into the buffer and return its length without
+    /// the terminator. Fails with `OtherError::ConsoleDriverLineTooLong` if the line does not fit
+    /// into the buffer; the rest of the line is left unread.
+    ///
+    /// The line is read in chunks of up to [READ_AHEAD_SIZE] bytes. Since the kernel completes a
+    /// read once its buffer is full, a line is only returned once the whole chunk containing its
+    /// terminator has arrived.
+    pub async fn read_line(&mut self, buffer: &mut [u8]) -> TockResult<usize> {
+        let mut len = 0;
+        loop {
+            if len == buffer.len() {
+                // Only the terminator fits into the buffer.
+                let mut byte = [0];
+                self.read_exact(&mut byte).await?;
+                if byte[0] != b'\n' {
+                    self.keep_read_ahead(&byte);
+                    return Err(OtherError::ConsoleDriverLineTooLong.into());
+                }
+                break;
+            }
+            let chunk_len = (buffer.len() - len).min(READ_AHEAD_SIZE);
+            let chunk = &mut buffer[len..len + chunk_len];
+            let num_bytes_read = self.read_some(chunk).await?;
+            if let Some(line_end) = chunk[..num_bytes_read].iter().position(|&b| b == b'\n') {
+                self.keep_read_ahead(&chunk[line_end + 1..num_bytes_read]);
+                len += line_end;
+                break;
+            }
+            len += num_bytes_read;
+        }
+        if len > 0 && buffer[len - 1] == b'\r' {
+            len -= 1;
+        }
+        Ok(len)
+    }
+
+    /// Like [read](Console::read), but fails if the kernel completes the read without any data
+    /// instead of returning 0.
+    async fn read_some(&mut self, buffer: &mut [u8]) -> TockResult<usize> {
+        let num_bytes_read = self.read(buffer).await?;
+        if num_bytes_read == 0 {
+            return Err(CommandError {
+                driver_number: DRIVER_NUMBER,
+                command_number: command_nr::READ,
+                arg1: buffer.len(),
+                arg2: 0,
+                error_code: ErrorCode::Fail,
+            }
+            .into());
+        }
+        Ok(num_bytes_read)
+    }
+
+    fn take_read_ahead(&mut self, buffer: &mut [u8]) -> usize {
+        let len = buffer.len().min(self.read_ahead_len);
+        buffer[..len].copy_from_slice(&self.read_ahead[..len]);
+        self.read_ahead.copy_within(len..self.read_ahead_len, 0);
+        self.read_ahead_len -= len;
+        len
+    }
+
+    /// Puts bytes read past the end of a line in front of the remaining read-ahead. They always
+    /// fit, since they are less than the bytes of the read they come from.
+    fn keep_read_ahead(&mut self, bytes: &[u8]) {
+        let len = self.read_ahead_len;
+        self.read_ahead.copy_within(..len, bytes.len());
+        self.read_ahead[..bytes.len()].copy_from_slice(bytes);
+        self.read_ahead_len += bytes.len();
+    }
+}
+
+/// Aborts a read which has not completed when dropped, e.g. because the read future has been
+/// dropped. Forgotten once the read has completed.
+struct PendingRead;
+
+impl Drop for PendingRead {
+    fn drop(&mut self) {
+        // Errors cannot be reported from here. The kernel stops writing into the buffer once it
+        // is unallowed anyway.
+        let _ = syscalls::command(DRIVER_NUMBER, command_nr::ABORT_READ, 0, 0);
+    }
 }
 
 impl fmt::Write for Console {
@@ -80,3 +258,440 @@ impl fmt::Write for Console {
         self.write(string).map_err(|_| fmt::Error)
     }
 }
+
+/// Print to the console. Unlike `writeln!`, it does not require the [ConsoleDriver], so it can
+/// be used anywhere, even if the app owns the drivers. Errors are ignored.
+///
+/// ```no_run
+/// libtock::print!("{} + {} = ", 1, 2);
+/// libtock::println!("{}", 1 + 2);
+/// ```
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => {
+        $crate::console::_print(format_args!($($arg)*))
+    };
+}
+
+/// Print to the console, followed by a newline. See [print!].
+#[macro_export]
+macro_rules! println {
+    () => {
+        $crate::print!("\n")
+    };
+    ($($arg:tt)*) => {
+        $crate::print!("{}\n", format_args!($($arg)*))
+    };
+}
+
+/// Print to the console. Tock has no separate error output, so this is the same as [print!].
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => {
+        $crate::print!($($arg)*)
+    };
+}
+
+/// Print to the console, followed by a newline. See [eprint!].
+#[macro_export]
+macro_rules! eprintln {
+    ($($arg:tt)*) => {
+        $crate::println!($($arg)*)
+    };
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    let _ = write_to_global_console(|console| Ok(fmt::Write::write_fmt(console, args)?));
+}
+
+/// Marks the console as in use while it exists. All consoles share the allow and subscribe
+/// numbers of the console driver, so a write must not start while another one is in progress.
+struct ConsoleInUse;
+
+impl ConsoleInUse {
+    fn mark() -> TockResult<ConsoleInUse> {
+        with_console_in_use(|in_use| {
+            if *in_use {
+                return Err(OtherError::ConsoleDriverBusy.into());
+            }
+            *in_use = true;
+            Ok(ConsoleInUse)
+        })
+    }
+}
+
+impl Drop for ConsoleInUse {
+    fn drop(&mut self) {
+        with_console_in_use(|in_use| *in_use = false);
+    }
+}
+
+#[cfg(test)]
+fn with_console_in_use<R, F: FnOnce(&mut bool) -> R>(f: F) -> R {
+    thread_local!(static CONSOLE_IN_USE: Cell<bool> = Cell::new(false));
+    CONSOLE_IN_USE.with(|console_in_use| {
+        let mut in_use = console_in_use.get();
+        let result = f(&mut in_use);
+        console_in_use.set(in_use);
+        result
+    })
+}
+
+#[cfg(not(test))]
+fn with_console_in_use<R, F: FnOnce(&mut bool) -> R>(f: F) -> R {
+    static mut CONSOLE_IN_USE: bool = false;
+    // Apps are single-threaded and the flag is not borrowed across system calls.
+    f(unsafe { &mut CONSOLE_IN_USE })
+}
+
+/// Writes using a console which is marked as in use for the whole closure, so consecutive
+/// writes are not interleaved with others. Fails with `OtherError::ConsoleDriverBusy` if the
+/// console is already in use, e.g. when printing from an upcall delivered while a write is
+/// waiting for the kernel.
+pub(crate) fn write_to_global_console<F: FnOnce(&mut Console) -> TockResult<()>>(
+    f: F,
+) -> TockResult<()> {
+    let mut console = Console::new();
+    console.in_use = Some(ConsoleInUse::mark()?);
+    f(&mut console)
+}
+
+#[cfg(test)]
+pub(crate) mod fake {
+    use super::*;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeDriver;
+    use crate::syscalls::CommandReturn;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+    use std::vec::Vec;
+
+    pub(crate) const DRIVER_NUMBER: usize = super::DRIVER_NUMBER;
+
+    /// Records the written text and completes reads as soon as enough input is available.
+    #[derive(Default)]
+    pub(crate) struct FakeConsole {
+        pub(crate) input: VecDeque<u8>,
+        pub(crate) output: Rc<RefCell<Vec<u8>>>,
+        pub(crate) aborted_while_allowed: Rc<Cell<bool>>,
+        pub(crate) num_reads: Rc<Cell<usize>>,
+        pending_read: Option<usize>,
+    }
+
+    impl FakeConsole {
+        pub(crate) fn with_input(input: &[u8]) -> FakeConsole {
+            FakeConsole {
+                input: input.iter().cloned().collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    impl FakeDriver for FakeConsole {
+        fn command(
+            &mut self,
+            handle: &mut DriverHandle,
+            command_number: usize,
+            len: usize,
+            _: usize,
+        ) -> CommandReturn {
+            match command_number {
+                command_nr::WRITE => {
+                    let text = handle
+                        .allowed_readonly_buffer(allow_nr::SHARE_BUFFER)
+                        .unwrap();
+                    self.output.borrow_mut().extend_from_slice(&text[..len]);
+                    handle.schedule_upcall(subscribe_nr::SET_ALARM, (len, 0, 0));
+                    CommandReturn::Success
+                }
+                command_nr::READ => {
+                    self.num_reads.set(self.num_reads.get() + 1);
+                    if len <= self.input.len() {
+                        let buffer = handle.allowed_buffer(allow_nr::READ_BUFFER).unwrap();
+                        for byte in buffer[..len].iter_mut() {
+                            *byte = self.input.pop_front().unwrap();
+                        }
+                        handle.schedule_upcall(subscribe_nr::READ_DONE, (0, len, 0));
+                    } else {
+                        self.pending_read = Some(len);
+                    }
+                    CommandReturn::Success
+                }
+                command_nr::ABORT_READ => {
+                    if self.pending_read.take().is_some() {
+                        let allowed = handle.allowed_buffer(allow_nr::READ_BUFFER).is_some();
+                        self.aborted_while_allowed.set(allowed);
+                    }
+                    CommandReturn::Success
+                }
+                _ => CommandReturn::Failure(ErrorCode::NoSupport),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::fake::FakeConsole;
+    use super::*;
+    use crate::result::TockError;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use crate::syscalls::CommandReturn;
+    use ::futures::future;
+    use core::fmt::Write;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    pub fn text_is_shared_without_copying() {
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let output_in_driver = output.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(
+            DRIVER_NUMBER,
+            move |handle: &mut DriverHandle, command_number, num_bytes, _| {
+                assert_eq!(command_number, command_nr::WRITE);
+                let buffer = handle
+                    .allowed_readonly_buffer(allow_nr::SHARE_BUFFER)
+                    .unwrap();
+                assert_eq!(buffer.len(), num_bytes);
+                output_in_driver.borrow_mut().push(buffer.to_vec());
+                handle.schedule_upcall(subscribe_nr::SET_ALARM, (num_bytes, 0, 0));
+                CommandReturn::Success
+            },
+        );
+
+        let text = "x".repeat(100);
+        let text_to_write = text.clone();
+        kernel
+            .run(|| {
+                let drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+                let mut console = drivers.console.create_console();
+                writeln!(console, "{}", text_to_write)
+            })
+            .unwrap();
+
+        assert_eq!(
+            *output.borrow(),
+            vec![text.into_bytes(), "\n".as_bytes().to_vec()]
+        );
+    }
+
+    #[test]
+    pub fn writes_without_progress_fail() {
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, |handle: &mut DriverHandle, _, _, _| {
+            handle.schedule_upcall(subscribe_nr::SET_ALARM, (0, 0, 0));
+            CommandReturn::Success
+        });
+
+        let result = kernel.run(|| {
+            let drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut console = drivers.console.create_console();
+            console.write("text")
+        });
+
+        match result {
+            Err(TockError::Command(CommandError {
+                command_number: command_nr::WRITE,
+                arg1: 4,
+                error_code: ErrorCode::Fail,
+                ..
+            })) => {}
+            other => panic!("Expected a failed write, got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn lines_are_read_without_terminator() {
+        let mut kernel = FakeKernel::new();
+        // The chunk containing the end of the second line also contains the following line.
+        kernel.add_driver(
+            DRIVER_NUMBER,
+            FakeConsole::with_input(b"hello\r\nworld\n!\n"),
+        );
+
+        let lines = kernel.run(|| {
+            let drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut console = drivers.console.create_console();
+            let mut buffer = [0; 8];
+            let mut lines = Vec::new();
+            for _ in 0..2 {
+                let len = unsafe { executor::block_on(console.read_line(&mut buffer)) }.unwrap();
+                lines.push(buffer[..len].to_vec());
+            }
+            lines
+        });
+
+        assert_eq!(lines, vec![b"hello".to_vec(), b"world".to_vec()]);
+    }
+
+    #[test]
+    pub fn lines_are_read_in_chunks_and_the_rest_is_kept() {
+        let fake_console = FakeConsole::with_input(b"first line\nsecond");
+        let num_reads = fake_console.num_reads.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, fake_console);
+
+        let (line, rest) = kernel.run(|| {
+            let drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut console = drivers.console.create_console();
+            let mut line = [0; 32];
+            let mut rest = [0; 6];
+            unsafe {
+                executor::block_on(async {
+                    let len = console.read_line(&mut line).await?;
+                    console.read_exact(&mut rest).await?;
+                    Ok::<_, crate::result::TockError>(line[..len].to_vec())
+                })
+            }
+            .map(|line| (line, rest))
+            .unwrap()
+        });
+
+        assert_eq!(line, b"first line".to_vec());
+        assert_eq!(&rest, b"second");
+        // One chunk with the line and the start of the rest, one read for the remaining byte.
+        assert_eq!(num_reads.get(), 2);
+    }
+
+    #[test]
+    pub fn too_long_lines_are_left_unread() {
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, FakeConsole::with_input(b"hello\n"));
+
+        let (result, rest) = kernel.run(|| {
+            let drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut console = drivers.console.create_console();
+            let mut buffer = [0; 3];
+            let result = unsafe { executor::block_on(console.read_line(&mut buffer)) };
+            let len = unsafe { executor::block_on(console.read_line(&mut buffer)) }.unwrap();
+            (result, buffer[..len].to_vec())
+        });
+
+        match result {
+            Err(TockError::Other(OtherError::ConsoleDriverLineTooLong)) => {}
+            other => panic!("Expected a too long line, got {:?}", other),
+        }
+        assert_eq!(rest, b"lo".to_vec());
+    }
+
+    #[test]
+    pub fn empty_reads_fail_instead_of_being_retried() {
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, |handle: &mut DriverHandle, _, _, _| {
+            handle.schedule_upcall(subscribe_nr::READ_DONE, (0, 0, 0));
+            CommandReturn::Success
+        });
+
+        let result = kernel.run(|| {
+            let drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut console = drivers.console.create_console();
+            let mut buffer = [0; 4];
+            unsafe { executor::block_on(console.read_exact(&mut buffer)) }
+        });
+
+        match result {
+            Err(TockError::Command(CommandError {
+                command_number: command_nr::READ,
+                error_code: ErrorCode::Fail,
+                ..
+            })) => {}
+            other => panic!("Expected a failed read, got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn dropped_reads_are_aborted_before_the_buffer_is_unallowed() {
+        let fake_console = FakeConsole::default();
+        let aborted_while_allowed = fake_console.aborted_while_allowed.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, fake_console);
+
+        kernel.run(|| {
+            let drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut console = drivers.console.create_console();
+            let mut buffer = [0; 4];
+            unsafe {
+                executor::block_on(futures::select(
+                    console.read(&mut buffer),
+                    future::ready(()),
+                ))
+            };
+        });
+
+        assert!(aborted_while_allowed.get());
+    }
+
+    #[test]
+    pub fn print_macros_do_not_require_the_console_driver() {
+        let fake_console = FakeConsole::default();
+        let output = fake_console.output.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, fake_console);
+
+        kernel.run(|| {
+            let _drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            crate::print!("{} + {} = ", 1, 2);
+            crate::println!("{}", 1 + 2);
+            crate::eprintln!();
+        });
+
+        assert_eq!(*output.borrow(), b"1 + 2 = 3\n\n");
+    }
+
+    #[test]
+    pub fn writes_from_upcalls_during_a_write_fail() {
+        const OTHER_DRIVER_NUMBER: usize = 0x99;
+        let fake_console = FakeConsole::default();
+        let output = fake_console.output.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, fake_console);
+        kernel.add_driver(OTHER_DRIVER_NUMBER, |_: &mut DriverHandle, _, _, _| {
+            CommandReturn::Success
+        });
+        // Delivered while the write below waits for the kernel.
+        kernel.schedule_upcall(OTHER_DRIVER_NUMBER, 0, (0, 0, 0));
+
+        let (outer_result, inner_result) = kernel.run(|| {
+            let drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut console = drivers.console.create_console();
+            let inner_result = Cell::new(None);
+            let mut print_from_upcall = |_| {
+                crate::println!("ignored");
+                inner_result.set(Some(write_to_global_console(|console| {
+                    console.write("inner")
+                })));
+            };
+            let subscription = syscalls::subscribe::<Identity1Consumer, _>(
+                OTHER_DRIVER_NUMBER,
+                0,
+                &mut print_from_upcall,
+            )
+            .unwrap();
+            let outer_result = console.write("outer");
+            mem::drop(subscription);
+            (outer_result, inner_result.take())
+        });
+
+        assert!(outer_result.is_ok());
+        match inner_result {
+            Some(Err(TockError::Other(OtherError::ConsoleDriverBusy))) => {}
+            other => panic!("Expected a busy console, got {:?}", other),
+        }
+        assert_eq!(*output.borrow(), b"outer");
+    }
+
+    #[test]
+    pub fn global_console_is_not_used_reentrantly() {
+        let result = write_to_global_console(|_| {
+            assert!(write_to_global_console(|_| Ok(())).is_err());
+            Ok(())
+        });
+        assert!(result.is_ok());
+        assert!(write_to_global_console(|_| Ok(())).is_ok());
+    }
+}
@@ -2,33 +2,27 @@
 
 mod low_level_debug;
 
-use crate::drivers;
+use crate::console;
 use libtock_core::debug as core_debug;
 
 pub use low_level_debug::*;
 
 pub fn println() {
     let buffer = [b'\n'];
-    let drivers = unsafe { drivers::retrieve_drivers_unsafe() };
-    let mut console = drivers.console.create_console();
-    let _ = console.write(&buffer);
+    let _ = console::write_to_global_console(|console| console.write(&buffer));
 }
 
 pub fn print_as_hex(value: usize) {
     let mut buffer = [b'\n'; 11];
     write_as_hex(&mut buffer, value);
-    let drivers = unsafe { drivers::retrieve_drivers_unsafe() };
-    let mut console = drivers.console.create_console();
-    let _ = console.write(buffer);
+    let _ = console::write_to_global_console(|console| console.write(buffer));
 }
 
 pub fn print_stack_pointer() {
     let mut buffer = [b'\n'; 15];
     buffer[0..4].clone_from_slice(b"SP: ");
     write_as_hex(&mut buffer[4..15], core_debug::get_stack_pointer());
-    let drivers = unsafe { drivers::retrieve_drivers_unsafe() };
-    let mut console = drivers.console.create_console();
-    let _ = console.write(buffer);
+    let _ = console::write_to_global_console(|console| console.write(buffer));
 }
 
 #[inline(always)]
@@ -48,9 +42,7 @@ pub unsafe fn dump_address(address: *const usize) {
         }
     }
     buffer[27] = b'\n';
-    let drivers = drivers::retrieve_drivers_unsafe();
-    let mut console = drivers.console.create_console();
-    let _ = console.write(&buffer);
+    let _ = console::write_to_global_console(|console| console.write(&buffer));
 }
 
 /// Dumps arbitrary memory regions.
@@ -3,6 +3,7 @@ use crate::buttons::ButtonsDriverFactory;
 use crate::console::ConsoleDriver;
 use crate::gpio::GpioDriverFactory;
 use crate::hmac::HmacDriverFactory;
+use crate::i2c::I2cDriverFactory;
 use crate::leds::LedsDriverFactory;
 use crate::result::OtherError;
 use crate::result::TockError;
@@ -13,6 +14,7 @@ use crate::sensors::HumiditySensor;
 use crate::sensors::TemperatureSensor;
 use crate::simple_ble::BleAdvertisingDriverFactory;
 use crate::simple_ble::BleScanningDriverFactory;
+use crate::spi::SpiDriverFactory;
 use crate::temperature::TemperatureDriverFactory;
 use crate::timer::DriverContext;
 use core::cell::Cell;
@@ -25,6 +27,7 @@ pub struct Drivers {
     pub timer: DriverContext,
     pub gpio: GpioDriverFactory,
     pub hmac: HmacDriverFactory,
+    pub i2c: I2cDriverFactory,
     pub temperature: TemperatureDriverFactory,
     pub buttons: ButtonsDriverFactory,
     pub adc: AdcDriverFactory,
@@ -35,6 +38,7 @@ pub struct Drivers {
     pub temperature_sensor: TemperatureSensor,
     pub humidity_sensor: HumiditySensor,
     pub ninedof: NinedofDriver,
+    pub spi: SpiDriverFactory,
 }
 
 /// Retrieve [Drivers] struct. Returns struct only once.
@@ -74,12 +78,14 @@ const DRIVERS: Drivers = Drivers {
     },
     gpio: GpioDriverFactory,
     hmac: HmacDriverFactory,
+    i2c: I2cDriverFactory,
     temperature: TemperatureDriverFactory,
     rng: RngDriver,
     ambient_light_sensor: AmbientLightSensor,
     temperature_sensor: TemperatureSensor,
     humidity_sensor: HumiditySensor,
     ninedof: NinedofDriver,
+    spi: SpiDriverFactory,
 };
 
 pub struct DriversAlreadyTakenError;
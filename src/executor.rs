@@ -1,4 +1,10 @@
+use crate::callback;
+use crate::result::OtherError;
+use crate::result::TockResult;
 use crate::syscalls;
+use core::cell::Cell;
+use core::cell::RefCell;
+use core::mem;
 use core::pin::Pin;
 use core::ptr;
 use core::task::Context;
@@ -6,33 +12,52 @@ use core::task::Poll;
 use core::task::RawWaker;
 use core::task::RawWakerVTable;
 use core::task::Waker;
+use futures::future;
 use futures::Future;
 
 /// # Safety
 ///
 /// [[block_on]] yields whenever a future cannot make any progress at present. Yielding is considered unsafe.
-pub unsafe fn block_on<T>(mut future: impl Future<Output = T>) -> T {
+pub unsafe fn block_on<T>(future: impl Future<Output = T>) -> T {
+    // Without a free wake slot, the future is polled after every upcall.
+    let wake_slot = WakeSlot::allocate();
+    let waker = match &wake_slot {
+        Some(wake_slot) => wake_slot.waker(),
+        None => Waker::from_raw(get_dummy_waker()),
+    };
+    let mut context = Context::from_waker(&waker);
+
+    // The future is dropped before the wake slot is released.
+    let mut future = future;
     // Contract described in the Rustdoc: "A value, once pinned, must remain pinned forever (...).".
     // IOW calling Pin::new_unchecked is safe as long as no &mut future is leaked after pinning.
     let mut pinned_future = Pin::new_unchecked(&mut future);
 
     loop {
-        match poll(pinned_future.as_mut()) {
-            Poll::Pending => syscalls::raw::yieldk(),
-            Poll::Ready(value) => {
+        let woken = match &wake_slot {
+            Some(wake_slot) => wake_slot.take_woken(),
+            None => true,
+        };
+        if woken {
+            if let Poll::Ready(value) = pinned_future.as_mut().poll(&mut context) {
                 return value;
             }
         }
+        // If the future has already been woken again, only pending upcalls are delivered.
+        let upcall_delivered = match &wake_slot {
+            Some(wake_slot) if wake_slot.is_woken() => syscalls::raw::yield_no_wait(),
+            _ => {
+                syscalls::raw::yieldk();
+                true
+            }
+        };
+        if upcall_delivered {
+            callback::wake_any_upcall_wakers();
+        }
     }
 }
 
-fn poll<F: Future>(pinned_future: Pin<&mut F>) -> Poll<F::Output> {
-    let waker = unsafe { Waker::from_raw(get_dummy_waker()) };
-    let mut context = Context::from_waker(&waker);
-    pinned_future.poll(&mut context)
-}
-
-// Since Tock OS comes with waking-up functionality built-in, we use dummy wakers that do nothing at all.
+// Used if no wake slot is available. The future is polled after every upcall instead.
 fn get_dummy_waker() -> RawWaker {
     fn clone(_x: *const ()) -> RawWaker {
         get_dummy_waker()
@@ -48,3 +73,521 @@ fn get_dummy_waker() -> RawWaker {
     // The wakers don't have any implementation, so the instance can simply be null.
     RawWaker::new(ptr::null(), &DUMMY_WAKER_VTABLE)
 }
+
+/// Every spawned task and every running [block_on] occupies a wake slot.
+const MAX_WAKE_SLOTS: usize = MAX_TASKS + 8;
+
+// Wakers encode the index and the generation of their wake slot.
+const INDEX_BITS: usize = 8;
+const INDEX_MASK: usize = (1 << INDEX_BITS) - 1;
+
+#[derive(Default)]
+struct WakeSlotState {
+    in_use: bool,
+    generation: usize,
+    woken: bool,
+    parent: Option<Waker>,
+}
+
+type WakeSlots = [WakeSlotState; MAX_WAKE_SLOTS];
+
+#[cfg(test)]
+fn with_wake_slots<R, F: FnOnce(&mut WakeSlots) -> R>(f: F) -> R {
+    thread_local!(static WAKE_SLOTS: RefCell<WakeSlots> = RefCell::new(Default::default()));
+    WAKE_SLOTS.with(|wake_slots| f(&mut wake_slots.borrow_mut()))
+}
+
+#[cfg(not(test))]
+fn with_wake_slots<R, F: FnOnce(&mut WakeSlots) -> R>(f: F) -> R {
+    static mut WAKE_SLOTS: Option<WakeSlots> = None;
+    // Apps are single-threaded and no wakers are woken or dropped while the slots are borrowed.
+    f(unsafe { WAKE_SLOTS.get_or_insert_with(Default::default) })
+}
+
+/// Records whether a task or a future run by [block_on] has been woken. Wakers refer to the
+/// slot by index and generation, so wakers outliving their task are harmless.
+struct WakeSlot {
+    index: usize,
+    generation: usize,
+}
+
+impl WakeSlot {
+    fn allocate() -> Option<WakeSlot> {
+        with_wake_slots(|wake_slots| {
+            let (index, state) = wake_slots
+                .iter_mut()
+                .enumerate()
+                .find(|(_, state)| !state.in_use)?;
+            state.in_use = true;
+            state.woken = true;
+            Some(WakeSlot {
+                index,
+                generation: state.generation,
+            })
+        })
+    }
+
+    fn waker(&self) -> Waker {
+        let data = self.index | (self.generation << INDEX_BITS);
+        unsafe { Waker::from_raw(raw_waker(data)) }
+    }
+
+    fn is_woken(&self) -> bool {
+        with_wake_slots(|wake_slots| wake_slots[self.index].woken)
+    }
+
+    fn take_woken(&self) -> bool {
+        with_wake_slots(|wake_slots| mem::replace(&mut wake_slots[self.index].woken, false))
+    }
+
+    /// The parent is woken together with the slot, e.g. the executor running the task.
+    fn set_parent(&self, parent: &Waker) {
+        let previous_parent = with_wake_slots(|wake_slots| {
+            let state = &mut wake_slots[self.index];
+            match &state.parent {
+                Some(current_parent) if current_parent.will_wake(parent) => None,
+                _ => state.parent.replace(parent.clone()),
+            }
+        });
+        mem::drop(previous_parent);
+    }
+}
+
+impl Drop for WakeSlot {
+    fn drop(&mut self) {
+        callback::unregister_waker(&self.waker());
+        let parent = with_wake_slots(|wake_slots| {
+            let state = &mut wake_slots[self.index];
+            state.in_use = false;
+            state.generation = state.generation.wrapping_add(1) & (usize::MAX >> INDEX_BITS);
+            state.parent.take()
+        });
+        mem::drop(parent);
+    }
+}
+
+static WAKER_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(clone_waker, wake_waker, wake_waker, drop_waker);
+
+fn raw_waker(data: usize) -> RawWaker {
+    RawWaker::new(data as *const (), &WAKER_VTABLE)
+}
+
+fn clone_waker(data: *const ()) -> RawWaker {
+    raw_waker(data as usize)
+}
+
+fn wake_waker(data: *const ()) {
+    let index = data as usize & INDEX_MASK;
+    let generation = data as usize >> INDEX_BITS;
+    let parent = with_wake_slots(|wake_slots| {
+        let state = &mut wake_slots[index];
+        if !state.in_use || state.generation != generation {
+            return None;
+        }
+        state.woken = true;
+        state.parent.clone()
+    });
+    if let Some(parent) = parent {
+        parent.wake();
+    }
+}
+
+fn drop_waker(_data: *const ()) {}
+
+/// Number of tasks an [Executor] can run concurrently in addition to the future passed to
+/// [Executor::run_until].
+pub const MAX_TASKS: usize = 8;
+
+/// Storage of a task spawned on an [Executor]. A task does not allocate. It has to be pinned
+/// before it can be spawned, e.g. using `futures::pin_mut!`.
+pub struct Task<F: Future> {
+    state: RefCell<TaskState<F>>,
+    // Declared after the state, s.t. the future is dropped before the wake slot is released.
+    wake_slot: RefCell<Option<WakeSlot>>,
+    joiner: RefCell<Option<Waker>>,
+}
+
+enum TaskState<F: Future> {
+    Running(F),
+    Finished(F::Output),
+    Joined,
+}
+
+impl<F: Future> Task<F> {
+    pub fn new(future: F) -> Task<F> {
+        Task {
+            state: RefCell::new(TaskState::Running(future)),
+            wake_slot: RefCell::new(None),
+            joiner: RefCell::new(None),
+        }
+    }
+}
+
+trait Runnable {
+    fn take_woken(&self) -> bool;
+
+    /// Polls the task and returns whether it has finished.
+    fn poll_task(&self, parent: &Waker) -> bool;
+}
+
+impl<F: Future> Runnable for Task<F> {
+    fn take_woken(&self) -> bool {
+        match &*self.wake_slot.borrow() {
+            Some(wake_slot) => wake_slot.take_woken(),
+            None => false,
+        }
+    }
+
+    fn poll_task(&self, parent: &Waker) -> bool {
+        let waker = match &*self.wake_slot.borrow() {
+            Some(wake_slot) => {
+                wake_slot.set_parent(parent);
+                wake_slot.waker()
+            }
+            None => return true,
+        };
+        let mut context = Context::from_waker(&waker);
+        let mut state = self.state.borrow_mut();
+        let output = match &mut *state {
+            // Tasks can only be spawned when pinned, so the future is never moved.
+            TaskState::Running(future) => {
+                match unsafe { Pin::new_unchecked(future) }.poll(&mut context) {
+                    Poll::Ready(output) => output,
+                    Poll::Pending => return false,
+                }
+            }
+            TaskState::Finished(_) | TaskState::Joined => return true,
+        };
+        *state = TaskState::Finished(output);
+        mem::drop(state);
+        mem::drop(self.wake_slot.borrow_mut().take());
+        if let Some(joiner) = self.joiner.borrow_mut().take() {
+            joiner.wake();
+        }
+        true
+    }
+}
+
+/// Resolves to the output of a spawned task once it has finished.
+pub struct JoinHandle<'a, F: Future> {
+    task: &'a Task<F>,
+}
+
+impl<'a, F: Future> JoinHandle<'a, F> {
+    pub fn is_finished(&self) -> bool {
+        match *self.task.state.borrow() {
+            TaskState::Running(_) => false,
+            TaskState::Finished(_) | TaskState::Joined => true,
+        }
+    }
+}
+
+impl<'a, F: Future> Future for JoinHandle<'a, F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.task.state.borrow_mut();
+        if let TaskState::Running(_) = *state {
+            *self.task.joiner.borrow_mut() = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        match mem::replace(&mut *state, TaskState::Joined) {
+            TaskState::Finished(output) => Poll::Ready(output),
+            _ => panic!("JoinHandle polled after completion"),
+        }
+    }
+}
+
+/// Runs up to [MAX_TASKS] tasks concurrently without requiring an allocator. The tasks make
+/// progress while a future passed to [Executor::run_until] or [Executor::run] is awaited, e.g.
+/// in the `async` main function. Only tasks which have been woken are polled.
+///
+/// ```no_run
+/// # use futures::pin_mut;
+/// # use libtock::executor::Executor;
+/// # use libtock::executor::Task;
+/// # use libtock::result::TockResult;
+/// # async fn doc() -> TockResult<()> {
+/// let task = Task::new(async { 42 });
+/// pin_mut!(task);
+/// let executor = Executor::new();
+/// let join_handle = executor.spawn(task.as_ref())?;
+/// assert_eq!(executor.run_until(join_handle).await, 42);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Executor<'a> {
+    tasks: [Cell<Option<&'a dyn Runnable>>; MAX_TASKS],
+}
+
+impl<'a> Default for Executor<'a> {
+    fn default() -> Self {
+        Executor::new()
+    }
+}
+
+impl<'a> Executor<'a> {
+    pub fn new() -> Executor<'a> {
+        Executor {
+            tasks: Default::default(),
+        }
+    }
+
+    /// Spawns a task. It will be polled while the executor is running.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the task has already been spawned.
+    pub fn spawn<F: Future + 'a>(&self, task: Pin<&'a Task<F>>) -> TockResult<JoinHandle<'a, F>> {
+        let task = task.get_ref();
+        if task.wake_slot.borrow().is_some() {
+            panic!("Task spawned twice");
+        }
+        let free_slot = self
+            .tasks
+            .iter()
+            .find(|slot| slot.get().is_none())
+            .ok_or(OtherError::ExecutorTaskSlotsExhausted)?;
+        let wake_slot = WakeSlot::allocate().ok_or(OtherError::ExecutorTaskSlotsExhausted)?;
+        *task.wake_slot.borrow_mut() = Some(wake_slot);
+        free_slot.set(Some(task));
+        Ok(JoinHandle { task })
+    }
+
+    /// Runs the spawned tasks until the given future resolves. Tasks which have not finished
+    /// until then are continued on the next run.
+    pub async fn run_until<F: Future>(&self, future: F) -> F::Output {
+        futures::pin_mut!(future);
+        future::poll_fn(|cx| loop {
+            if let Poll::Ready(output) = future.as_mut().poll(cx) {
+                return Poll::Ready(output);
+            }
+            // A finished task may allow the future to complete.
+            if !self.poll_tasks(cx.waker()) {
+                return Poll::Pending;
+            }
+        })
+        .await
+    }
+
+    /// Runs the spawned tasks until all of them have finished.
+    pub async fn run(&self) {
+        self.run_until(future::poll_fn(|_| {
+            if self.tasks.iter().all(|slot| slot.get().is_none()) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }))
+        .await
+    }
+
+    /// Polls the woken tasks until none of them is woken anymore and returns whether any task
+    /// has finished.
+    fn poll_tasks(&self, parent: &Waker) -> bool {
+        let mut finished = false;
+        loop {
+            let mut polled = false;
+            for slot in self.tasks.iter() {
+                if let Some(task) = slot.get() {
+                    if task.take_woken() {
+                        polled = true;
+                        if task.poll_task(parent) {
+                            slot.set(None);
+                            finished = true;
+                        }
+                    }
+                }
+            }
+            if !polled {
+                return finished;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::callback::Identity0Consumer;
+    use crate::futures;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use crate::syscalls::CommandReturn;
+    use ::futures::pin_mut;
+
+    /// A future which is pending the first time it is polled.
+    async fn yield_once() {
+        let mut yielded = false;
+        future::poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Subscribes to the driver, triggers an upcall and waits for it.
+    async fn wait_for_upcall(driver_number: usize, trigger: bool) -> TockResult<()> {
+        let upcall_delivered = Cell::new(false);
+        let mut callback = || upcall_delivered.set(true);
+        let subscription =
+            syscalls::subscribe::<Identity0Consumer, _>(driver_number, 0, &mut callback)?;
+        if trigger {
+            syscalls::command(driver_number, 0, 0, 0)?;
+        }
+        futures::wait_until_on_upcall(driver_number, 0, || upcall_delivered.get()).await;
+        mem::drop(subscription);
+        Ok(())
+    }
+
+    thread_local!(static UPCALLS_DELIVERED: Cell<usize> = Cell::new(0));
+
+    /// Like [wait_for_upcall], but the same userdata is passed to the kernel for all
+    /// subscriptions.
+    async fn wait_for_upcall_of_zero_sized_callback(
+        driver_number: usize,
+        trigger: bool,
+    ) -> TockResult<()> {
+        // Boxes of zero-sized values all have the same dangling address.
+        let mut callback = Box::new(|| UPCALLS_DELIVERED.with(|count| count.set(count.get() + 1)));
+        let subscription =
+            syscalls::subscribe::<Identity0Consumer, _>(driver_number, 0, &mut *callback)?;
+        let delivered_before = UPCALLS_DELIVERED.with(Cell::get);
+        if trigger {
+            syscalls::command(driver_number, 0, 0, 0)?;
+        }
+        futures::wait_until_on_upcall(driver_number, 0, || {
+            UPCALLS_DELIVERED.with(Cell::get) > delivered_before
+        })
+        .await;
+        mem::drop(subscription);
+        Ok(())
+    }
+
+    #[test]
+    pub fn spawned_tasks_run_concurrently_and_can_be_joined() {
+        let log = RefCell::new(Vec::new());
+        let task_a = Task::new(async {
+            log.borrow_mut().push("a1");
+            yield_once().await;
+            log.borrow_mut().push("a2");
+            1
+        });
+        let task_b = Task::new(async {
+            log.borrow_mut().push("b1");
+            yield_once().await;
+            log.borrow_mut().push("b2");
+            2
+        });
+        pin_mut!(task_a);
+        pin_mut!(task_b);
+
+        let executor = Executor::new();
+        let result = unsafe {
+            block_on(executor.run_until(async {
+                let join_a = executor.spawn(task_a.as_ref()).unwrap();
+                let join_b = executor.spawn(task_b.as_ref()).unwrap();
+                join_a.await + join_b.await
+            }))
+        };
+
+        assert_eq!(result, 3);
+        assert_eq!(*log.borrow(), vec!["a1", "b1", "a2", "b2"]);
+    }
+
+    #[test]
+    pub fn run_waits_for_all_tasks() {
+        let finished = Cell::new(0);
+        let task_a = Task::new(async {
+            yield_once().await;
+            finished.set(finished.get() + 1);
+        });
+        let task_b = Task::new(async { finished.set(finished.get() + 1) });
+        pin_mut!(task_a);
+        pin_mut!(task_b);
+
+        let executor = Executor::new();
+        let join_a = executor.spawn(task_a.as_ref()).unwrap();
+        executor.spawn(task_b.as_ref()).unwrap();
+        unsafe { block_on(executor.run()) };
+
+        assert_eq!(finished.get(), 2);
+        assert!(join_a.is_finished());
+    }
+
+    #[test]
+    pub fn spawning_fails_if_all_slots_are_taken() {
+        let tasks: Vec<_> = (0..=MAX_TASKS)
+            .map(|_| Task::new(future::pending::<()>()))
+            .collect();
+        let executor = Executor::new();
+        // The tasks are not moved until the end of the test.
+        let results: Vec<_> = tasks
+            .iter()
+            .map(|task| executor.spawn(unsafe { Pin::new_unchecked(task) }))
+            .collect();
+        assert!(results[..MAX_TASKS].iter().all(Result::is_ok));
+        assert!(results[MAX_TASKS].is_err());
+    }
+
+    #[test]
+    pub fn upcalls_of_subscriptions_sharing_their_userdata_wake_all_of_them() {
+        let mut kernel = FakeKernel::new();
+        for &driver_number in [0x10, 0x11].iter() {
+            kernel.add_driver(driver_number, |handle: &mut DriverHandle, _, _, _| {
+                handle.schedule_upcall(0, (0, 0, 0));
+                CommandReturn::Success
+            });
+        }
+
+        kernel
+            .run(|| {
+                let task_a = Task::new(wait_for_upcall_of_zero_sized_callback(0x10, false));
+                let task_b = Task::new(wait_for_upcall_of_zero_sized_callback(0x11, true));
+                pin_mut!(task_a);
+                pin_mut!(task_b);
+                let executor = Executor::new();
+                executor.spawn(task_a.as_ref()).unwrap();
+                let join_b = executor.spawn(task_b.as_ref()).unwrap();
+                unsafe { block_on(executor.run_until(join_b)) }
+            })
+            .unwrap();
+    }
+
+    #[test]
+    pub fn upcalls_only_wake_the_waiting_task() {
+        let mut kernel = FakeKernel::new();
+        for &driver_number in [0x10, 0x11].iter() {
+            kernel.add_driver(driver_number, |handle: &mut DriverHandle, _, _, _| {
+                handle.schedule_upcall(0, (0, 0, 0));
+                CommandReturn::Success
+            });
+        }
+
+        let polls_of_b = Cell::new(0);
+        kernel
+            .run(|| {
+                let task_a = Task::new(wait_for_upcall(0x10, true));
+                let mut waiting_for_upcall = Box::pin(wait_for_upcall(0x11, false));
+                let task_b = Task::new(future::poll_fn(|cx| {
+                    polls_of_b.set(polls_of_b.get() + 1);
+                    waiting_for_upcall.as_mut().poll(cx)
+                }));
+                pin_mut!(task_a);
+                pin_mut!(task_b);
+                let executor = Executor::new();
+                let join_a = executor.spawn(task_a.as_ref()).unwrap();
+                executor.spawn(task_b.as_ref()).unwrap();
+                unsafe { block_on(executor.run_until(join_a)) }
+            })
+            .unwrap();
+
+        assert_eq!(polls_of_b.get(), 1);
+    }
+}
@@ -2,28 +2,93 @@ use core::future::Future;
 use core::pin::Pin;
 use core::task::Context;
 use core::task::Poll;
+use libtock_core::callback;
 
+pub use ::futures::future::Either;
+
+/// Waits until the condition holds. The condition is checked after every upcall. Prefer
+/// [wait_until_on_upcall()] if the condition only changes in the upcall of a single
+/// subscription.
 // TODO: Consider using FnMut
 pub async fn wait_until<F: Fn() -> bool>(condition: F) {
     wait_for_value(move || if condition() { Some(()) } else { None }).await
 }
 
+/// Waits until the value provider returns a value. It is called after every upcall.
 pub async fn wait_for_value<T, F: Fn() -> Option<T>>(value_provider: F) -> T {
-    WaitForValue { value_provider }.await
+    WaitForValue {
+        value_provider,
+        upcall: None,
+    }
+    .await
+}
+
+/// Waits until the condition holds. The condition is only checked after upcalls of the given
+/// subscription, which has to be created using [syscalls::subscribe()](crate::syscalls::subscribe).
+pub async fn wait_until_on_upcall<F: Fn() -> bool>(
+    driver_number: usize,
+    subscribe_number: usize,
+    condition: F,
+) {
+    wait_for_value_on_upcall(driver_number, subscribe_number, move || {
+        if condition() {
+            Some(())
+        } else {
+            None
+        }
+    })
+    .await
+}
+
+/// Waits until the value provider returns a value. It is only called after upcalls of the given
+/// subscription, which has to be created using [syscalls::subscribe()](crate::syscalls::subscribe).
+pub async fn wait_for_value_on_upcall<T, F: Fn() -> Option<T>>(
+    driver_number: usize,
+    subscribe_number: usize,
+    value_provider: F,
+) -> T {
+    WaitForValue {
+        value_provider,
+        upcall: Some((driver_number, subscribe_number)),
+    }
+    .await
+}
+
+/// Polls both futures until one of them completes and drops the other one. If both can complete,
+/// `first` wins.
+pub async fn select<A: Future, B: Future>(first: A, second: B) -> Either<A::Output, B::Output> {
+    ::futures::pin_mut!(first);
+    ::futures::pin_mut!(second);
+    ::futures::future::poll_fn(|cx| {
+        if let Poll::Ready(output) = first.as_mut().poll(cx) {
+            return Poll::Ready(Either::Left(output));
+        }
+        if let Poll::Ready(output) = second.as_mut().poll(cx) {
+            return Poll::Ready(Either::Right(output));
+        }
+        Poll::Pending
+    })
+    .await
 }
 
 struct WaitForValue<F> {
     value_provider: F,
+    upcall: Option<(usize, usize)>,
 }
 
 impl<T, F: Fn() -> Option<T>> Future for WaitForValue<F> {
     type Output = T;
 
-    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         if let Some(value) = (self.value_provider)() {
-            Poll::Ready(value)
-        } else {
-            Poll::Pending
+            return Poll::Ready(value);
+        }
+        match self.upcall {
+            Some((driver_number, subscribe_number)) => {
+                callback::register_upcall_waker(driver_number, subscribe_number, cx.waker())
+            }
+            None => callback::register_any_upcall_waker(cx.waker()),
         }
+        Poll::Pending
     }
 }
@@ -1,6 +1,8 @@
 use crate::callback::CallbackSubscription;
 use crate::callback::Consumer;
 use crate::result::OtherError;
+#[cfg(feature = "embedded-hal")]
+use crate::result::TockError;
 use crate::result::TockResult;
 use crate::syscalls;
 use core::marker::PhantomData;
@@ -30,7 +32,7 @@ pub struct GpioDriverFactory;
 impl GpioDriverFactory {
     pub fn init_driver(&mut self) -> TockResult<GpioDriver> {
         let driver = GpioDriver {
-            num_gpios: syscalls::command(DRIVER_NUMBER, command_nr::COUNT, 0, 0)?,
+            num_gpios: syscalls::command_u32(DRIVER_NUMBER, command_nr::COUNT, 0, 0)? as usize,
             lifetime: PhantomData,
         };
         Ok(driver)
@@ -209,7 +211,8 @@ impl<'a> GpioRead<'a> {
     }
 
     pub fn read(&self) -> TockResult<GpioState> {
-        let button_state = syscalls::command(DRIVER_NUMBER, command_nr::READ, self.gpio_num, 0)?;
+        let button_state =
+            syscalls::command_u32(DRIVER_NUMBER, command_nr::READ, self.gpio_num, 0)?;
         match button_state {
             0 => Ok(GpioState::Low),
             1 => Ok(GpioState::High),
@@ -257,3 +260,126 @@ pub enum TriggerType {
     RisingEdge = 1,
     FallingEdge = 2,
 }
+
+#[cfg(feature = "embedded-hal")]
+impl<'a> embedded_hal::digital::v2::OutputPin for GpioWrite<'a> {
+    type Error = TockError;
+
+    fn set_low(&mut self) -> TockResult<()> {
+        GpioWrite::set_low(self)
+    }
+
+    fn set_high(&mut self) -> TockResult<()> {
+        GpioWrite::set_high(self)
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<'a> embedded_hal::digital::v2::ToggleableOutputPin for GpioWrite<'a> {
+    type Error = TockError;
+
+    fn toggle(&mut self) -> TockResult<()> {
+        GpioWrite::toggle(self)
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<'a> embedded_hal::digital::v2::InputPin for GpioRead<'a> {
+    type Error = TockError;
+
+    fn is_high(&self) -> TockResult<bool> {
+        Ok(self.read()? == GpioState::High)
+    }
+
+    fn is_low(&self) -> TockResult<bool> {
+        Ok(self.read()? == GpioState::Low)
+    }
+}
+
+#[cfg(all(test, feature = "embedded-hal"))]
+mod test {
+    use super::*;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use crate::syscalls::CommandReturn;
+    use embedded_hal::digital::v2::InputPin;
+    use embedded_hal::digital::v2::OutputPin;
+    use embedded_hal::digital::v2::ToggleableOutputPin;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec::Vec;
+
+    /// Records the commands and reads the pin number modulo 3 as the state of input pins.
+    fn fake_gpio(commands: Rc<RefCell<Vec<(usize, usize)>>>) -> FakeKernel {
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(
+            DRIVER_NUMBER,
+            move |_: &mut DriverHandle, command_number, gpio_num, _| match command_number {
+                command_nr::COUNT => CommandReturn::SuccessU32(4),
+                command_nr::READ => CommandReturn::SuccessU32(gpio_num as u32 % 3),
+                _ => {
+                    commands.borrow_mut().push((command_number, gpio_num));
+                    CommandReturn::Success
+                }
+            },
+        );
+        kernel
+    }
+
+    #[test]
+    pub fn output_pins_are_set_by_the_driver() {
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        let kernel = fake_gpio(commands.clone());
+
+        kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut gpio_driver = drivers.gpio.init_driver().unwrap();
+            let mut gpio = gpio_driver.gpios().nth(1).unwrap();
+            let mut pin = gpio.enable_output().unwrap();
+            OutputPin::set_high(&mut pin).unwrap();
+            OutputPin::set_low(&mut pin).unwrap();
+            ToggleableOutputPin::toggle(&mut pin).unwrap();
+        });
+
+        assert_eq!(
+            *commands.borrow(),
+            vec![
+                (command_nr::ENABLE_OUTPUT, 1),
+                (command_nr::SET_HIGH, 1),
+                (command_nr::SET_LOW, 1),
+                (command_nr::TOGGLE, 1),
+                (command_nr::DISABLE, 1),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn input_pins_are_read_by_the_driver() {
+        let kernel = fake_gpio(Rc::default());
+
+        let (low, high, invalid) = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut gpio_driver = drivers.gpio.init_driver().unwrap();
+            let mut gpios = gpio_driver.gpios();
+            let read = |gpio: Option<Gpio>| {
+                let mut gpio = gpio.unwrap();
+                gpio.enable_input(ResistorMode::PullNone)
+                    .map(|pin| (pin.is_low(), pin.is_high()))
+                    .unwrap()
+            };
+            (read(gpios.next()), read(gpios.next()), read(gpios.next()))
+        });
+
+        match (low, high, invalid) {
+            (
+                (Ok(true), Ok(false)),
+                (Ok(false), Ok(true)),
+                (
+                    Err(TockError::Other(OtherError::GpioDriverInvalidState)),
+                    Err(TockError::Other(OtherError::GpioDriverInvalidState)),
+                ),
+            ) => {}
+            other => panic!("Unexpected pin states {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use crate::callback::Identity1Consumer;
+#[cfg(feature = "embedded-hal")]
+use crate::executor;
+use crate::futures;
+use crate::result::CommandError;
+use crate::result::ErrorCode;
+use crate::result::OtherError;
+#[cfg(feature = "embedded-hal")]
+use crate::result::TockError;
+use crate::result::TockResult;
+use crate::syscalls;
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::mem;
+
+const DRIVER_NUMBER: usize = 0x20003;
+
+/// The maximum number of bytes written or read by a single transfer.
+pub const BUFFER_SIZE: usize = 64;
+
+/// The highest 7-bit device address.
+const MAX_ADDRESS: u8 = 0x7f;
+
+mod command_nr {
+    pub const CHECK_PRESENT: usize = 0;
+    pub const WRITE: usize = 1;
+    pub const READ: usize = 2;
+    pub const WRITE_READ: usize = 3;
+}
+
+mod subscribe_nr {
+    pub const TRANSFER_DONE: usize = 0;
+}
+
+mod allow_nr {
+    pub const BUFFER: usize = 1;
+}
+
+#[non_exhaustive]
+pub struct I2cDriverFactory;
+
+impl I2cDriverFactory {
+    pub fn init_driver(&mut self) -> TockResult<I2cMaster> {
+        syscalls::command(DRIVER_NUMBER, command_nr::CHECK_PRESENT, 0, 0)?;
+        let i2c_master = I2cMaster {
+            buffer: [0; BUFFER_SIZE],
+            lifetime: PhantomData,
+        };
+        Ok(i2c_master)
+    }
+}
+
+/// Transfers data to and from devices on the I2C bus using the I2C master capsule. Devices are
+/// identified by their 7-bit address. If a device does not acknowledge a transfer, the transfer
+/// fails with `OtherError::I2cDriverNak`.
+///
+/// The data is copied into a buffer shared with the kernel, so transfers are limited to
+/// [BUFFER_SIZE] bytes in each direction. If the future of a transfer is dropped before the
+/// transfer has completed, the capsule remains busy until the transfer is over.
+pub struct I2cMaster<'a> {
+    buffer: [u8; BUFFER_SIZE],
+    lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> I2cMaster<'a> {
+    /// Write the data to the device.
+    pub async fn write(&mut self, address: u8, data: &[u8]) -> TockResult<()> {
+        check_transfer(address, data.len())?;
+        self.buffer[..data.len()].copy_from_slice(data);
+        self.transfer(command_nr::WRITE, address as usize, data.len())
+            .await
+    }
+
+    /// Read `buffer.len()` bytes from the device.
+    pub async fn read(&mut self, address: u8, buffer: &mut [u8]) -> TockResult<()> {
+        check_transfer(address, buffer.len())?;
+        self.transfer(command_nr::READ, address as usize, buffer.len())
+            .await?;
+        buffer.copy_from_slice(&self.buffer[..buffer.len()]);
+        Ok(())
+    }
+
+    /// Write the data to the device and read `buffer.len()` bytes afterwards without releasing
+    /// the bus in between, e.g. to read a register of the device.
+    pub async fn write_read(
+        &mut self,
+        address: u8,
+        data: &[u8],
+        buffer: &mut [u8],
+    ) -> TockResult<()> {
+        check_transfer(address, data.len())?;
+        check_transfer(address, buffer.len())?;
+        self.buffer[..data.len()].copy_from_slice(data);
+        self.transfer(
+            command_nr::WRITE_READ,
+            address as usize | (data.len() << 8),
+            buffer.len(),
+        )
+        .await?;
+        buffer.copy_from_slice(&self.buffer[..buffer.len()]);
+        Ok(())
+    }
+
+    async fn transfer(
+        &mut self,
+        command_number: usize,
+        arg1: usize,
+        arg2: usize,
+    ) -> TockResult<()> {
+        let shared_memory = syscalls::allow(DRIVER_NUMBER, allow_nr::BUFFER, &mut self.buffer)?;
+
+        let transfer_status = Cell::new(None);
+        let mut transfer_done = |status| transfer_status.set(Some(status));
+        let subscription = syscalls::subscribe::<Identity1Consumer, _>(
+            DRIVER_NUMBER,
+            subscribe_nr::TRANSFER_DONE,
+            &mut transfer_done,
+        )?;
+
+        syscalls::command(DRIVER_NUMBER, command_number, arg1, arg2)?;
+
+        let status =
+            futures::wait_for_value_on_upcall(DRIVER_NUMBER, subscribe_nr::TRANSFER_DONE, || {
+                transfer_status.get()
+            })
+            .await;
+
+        mem::drop(subscription);
+        mem::drop(shared_memory);
+
+        match status {
+            0 => Ok(()),
+            status => match ErrorCode::from_usize(status) {
+                Some(ErrorCode::NoAck) => Err(OtherError::I2cDriverNak.into()),
+                error_code => Err(CommandError {
+                    driver_number: DRIVER_NUMBER,
+                    command_number,
+                    arg1,
+                    arg2,
+                    error_code: error_code.unwrap_or(ErrorCode::BadRVal),
+                }
+                .into()),
+            },
+        }
+    }
+}
+
+fn check_transfer(address: u8, len: usize) -> TockResult<()> {
+    if address > MAX_ADDRESS {
+        return Err(OtherError::I2cDriverInvalidAddress.into());
+    }
+    if len > BUFFER_SIZE {
+        return Err(OtherError::I2cDriverTransferTooLong.into());
+    }
+    Ok(())
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<'a> embedded_hal::blocking::i2c::Write for I2cMaster<'a> {
+    type Error = TockError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> TockResult<()> {
+        unsafe { executor::block_on(I2cMaster::write(self, address, bytes)) }
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<'a> embedded_hal::blocking::i2c::Read for I2cMaster<'a> {
+    type Error = TockError;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> TockResult<()> {
+        unsafe { executor::block_on(I2cMaster::read(self, address, buffer)) }
+    }
+}
+
+#[cfg(feature = "embedded-hal")]
+impl<'a> embedded_hal::blocking::i2c::WriteRead for I2cMaster<'a> {
+    type Error = TockError;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> TockResult<()> {
+        unsafe { executor::block_on(I2cMaster::write_read(self, address, bytes, buffer)) }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::executor;
+    use crate::result::TockError;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeDriver;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use crate::syscalls::CommandReturn;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const DEVICE_ADDRESS: u8 = 0x48;
+
+    /// A device whose registers are selected by the first byte written to it.
+    #[derive(Default)]
+    struct FakeDevice {
+        registers: [u8; 16],
+        register: usize,
+        writes: Rc<RefCell<Vec<Vec<u8>>>>,
+    }
+
+    impl FakeDevice {
+        fn transfer(&mut self, buffer: &mut [u8], write_len: usize, read_len: usize) {
+            if write_len > 0 {
+                self.writes.borrow_mut().push(buffer[..write_len].to_vec());
+                self.register = buffer[0] as usize;
+            }
+            for byte in buffer[..read_len].iter_mut() {
+                *byte = self.registers[self.register];
+                self.register += 1;
+            }
+        }
+    }
+
+    impl FakeDriver for FakeDevice {
+        fn command(
+            &mut self,
+            handle: &mut DriverHandle,
+            command_number: usize,
+            arg1: usize,
+            arg2: usize,
+        ) -> CommandReturn {
+            let (write_len, read_len) = match command_number {
+                command_nr::CHECK_PRESENT => return CommandReturn::Success,
+                command_nr::WRITE => (arg2, 0),
+                command_nr::READ => (0, arg2),
+                command_nr::WRITE_READ => (arg1 >> 8, arg2),
+                _ => return CommandReturn::Failure(ErrorCode::NoSupport),
+            };
+            if arg1 & 0xff != DEVICE_ADDRESS as usize {
+                handle.schedule_upcall(
+                    subscribe_nr::TRANSFER_DONE,
+                    (ErrorCode::NoAck as usize, 0, 0),
+                );
+                return CommandReturn::Success;
+            }
+            let buffer = handle.allowed_buffer(allow_nr::BUFFER).unwrap();
+            self.transfer(buffer, write_len, read_len);
+            handle.schedule_upcall(subscribe_nr::TRANSFER_DONE, (0, 0, 0));
+            CommandReturn::Success
+        }
+    }
+
+    #[test]
+    pub fn data_is_written_to_the_device() {
+        let device = FakeDevice::default();
+        let writes = device.writes.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, device);
+
+        kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut i2c = drivers.i2c.init_driver().unwrap();
+            unsafe { executor::block_on(i2c.write(DEVICE_ADDRESS, &[1, 2, 3])) }.unwrap();
+        });
+
+        assert_eq!(*writes.borrow(), vec![vec![1, 2, 3]]);
+    }
+
+    #[test]
+    pub fn registers_are_read_after_writing_their_address() {
+        let mut device = FakeDevice::default();
+        device.registers[4..8].copy_from_slice(&[10, 11, 12, 13]);
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, device);
+
+        let (registers, next_registers) = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut i2c = drivers.i2c.init_driver().unwrap();
+            let mut registers = [0; 3];
+            let mut next_registers = [0; 1];
+            unsafe {
+                executor::block_on(async {
+                    i2c.write_read(DEVICE_ADDRESS, &[4], &mut registers).await?;
+                    i2c.read(DEVICE_ADDRESS, &mut next_registers).await
+                })
+            }
+            .unwrap();
+            (registers, next_registers)
+        });
+
+        assert_eq!(registers, [10, 11, 12]);
+        assert_eq!(next_registers, [13]);
+    }
+
+    #[test]
+    pub fn unacknowledged_transfers_fail_with_nak() {
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, FakeDevice::default());
+
+        let result = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut i2c = drivers.i2c.init_driver().unwrap();
+            unsafe { executor::block_on(i2c.write(DEVICE_ADDRESS + 1, &[1])) }
+        });
+
+        match result {
+            Err(TockError::Other(OtherError::I2cDriverNak)) => {}
+            other => panic!("Expected a NAK, got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn invalid_transfers_are_rejected_without_calling_the_kernel() {
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, FakeDevice::default());
+
+        let results = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut i2c = drivers.i2c.init_driver().unwrap();
+            let mut buffer = [0; BUFFER_SIZE + 1];
+            unsafe {
+                executor::block_on(async {
+                    (
+                        i2c.write(0x80, &[1]).await,
+                        i2c.read(DEVICE_ADDRESS, &mut buffer).await,
+                    )
+                })
+            }
+        });
+
+        match results {
+            (
+                Err(TockError::Other(OtherError::I2cDriverInvalidAddress)),
+                Err(TockError::Other(OtherError::I2cDriverTransferTooLong)),
+            ) => {}
+            other => panic!("Expected invalid transfers, got {:?}", other),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use crate::result::OutOfRangeError;
 use crate::result::TockResult;
 use crate::syscalls::command;
+use crate::syscalls::command_u32;
 use core::marker::PhantomData;
 
 const DRIVER_NUMBER: usize = 0x00002;
@@ -18,7 +19,7 @@ pub struct LedsDriverFactory;
 impl LedsDriverFactory {
     pub fn init_driver(&mut self) -> TockResult<LedsDriver> {
         let driver = LedsDriver {
-            num_leds: command(DRIVER_NUMBER, command_nr::COUNT, 0, 0)?,
+            num_leds: command_u32(DRIVER_NUMBER, command_nr::COUNT, 0, 0)? as usize,
             lifetime: PhantomData,
         };
         Ok(driver)
@@ -135,16 +136,17 @@ mod test {
     use crate::result::TockResult;
     use crate::syscalls;
     use crate::syscalls::raw::Event;
+    use crate::syscalls::CommandReturn;
 
     #[test]
     pub fn single_led_can_be_enabled() {
         let events = syscalls::raw::run_recording_events::<TockResult<()>, _>(|next_return| {
             let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
 
-            next_return.set(1);
+            next_return.set(CommandReturn::SuccessU32(1));
 
             let leds_driver = drivers.leds.init_driver()?;
-            next_return.set(0);
+            next_return.set(CommandReturn::Success);
 
             let led = leds_driver.get(0)?;
             led.on()?;
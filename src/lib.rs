@@ -1,6 +1,7 @@
 #![cfg_attr(not(test), no_std)]
 
 pub mod adc;
+pub mod binlog;
 pub mod ble_composer;
 pub mod ble_parser;
 pub mod buttons;
@@ -12,13 +13,25 @@ pub mod executor;
 pub mod futures;
 pub mod gpio;
 pub mod hmac;
+pub mod i2c;
 pub mod leds;
+#[cfg(feature = "log")]
+pub mod logger;
+#[cfg(all(
+    feature = "console_panic_handler",
+    any(target_arch = "arm", target_arch = "riscv32")
+))]
+mod panic_handler;
 pub mod result;
 pub mod rng;
 pub mod sensors;
+pub mod shell;
 pub mod simple_ble;
+pub mod spi;
+pub mod sync;
 pub mod temperature;
 pub mod timer;
+pub mod timer_service;
 
 pub use drivers::retrieve_drivers;
 pub use libtock_codegen::main;
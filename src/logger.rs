@@ -0,0 +1,185 @@
+//! Backend for the `log` crate writing to the console. Enable it with the `log` feature and
+//! register it using [init()] or `#[libtock::main(log = "info")]`.
+//!
+//! Records above the level passed to [ConsoleLogger::new] are discarded at runtime. To remove
+//! log statements from the binary, enable one of the `max_level_*` or `release_max_level_*`
+//! features of the `log` crate.
+//!
+//! ```no_run
+//! # use libtock::logger;
+//! # use libtock::logger::ConsoleLogger;
+//! # use libtock::logger::LevelFilter;
+//! # use libtock::result::TockResult;
+//! # fn doc() -> TockResult<()> {
+//! logger::init(ConsoleLogger::new(LevelFilter::Info).with_timestamps())?;
+//! log::info!("Started");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::console;
+use crate::result::OtherError;
+use crate::result::TockResult;
+use crate::timer;
+use core::fmt::Write;
+use log::Log;
+use log::Metadata;
+use log::Record;
+
+pub use log::LevelFilter;
+
+/// Writes log records to the console as `INFO  [target] message`, optionally prefixed by the
+/// uptime in seconds.
+#[derive(Copy, Clone)]
+pub struct ConsoleLogger {
+    max_level: LevelFilter,
+    timestamps: bool,
+}
+
+impl ConsoleLogger {
+    pub const fn new(max_level: LevelFilter) -> ConsoleLogger {
+        ConsoleLogger {
+            max_level,
+            timestamps: false,
+        }
+    }
+
+    /// Prefix the records with the time since the clock of the timer started, see
+    /// [Instant](crate::timer::Instant).
+    pub const fn with_timestamps(self) -> ConsoleLogger {
+        ConsoleLogger {
+            timestamps: true,
+            ..self
+        }
+    }
+
+    fn write_record(&self, console: &mut console::Console, record: &Record) -> TockResult<()> {
+        if self.timestamps {
+            if let Ok(ms) = clock_value_ms() {
+                write!(console, "[{:4}.{:03}] ", ms / 1000, ms % 1000)?;
+            }
+        }
+        writeln!(
+            console,
+            "{:5} [{}] {}",
+            record.level(),
+            record.target(),
+            record.args()
+        )?;
+        Ok(())
+    }
+}
+
+impl Log for ConsoleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.max_level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // Records logged while the console is in use, e.g. from an upcall, are dropped.
+        let _ = console::write_to_global_console(|console| self.write_record(console, record));
+    }
+
+    fn flush(&self) {}
+}
+
+fn clock_value_ms() -> TockResult<u64> {
+    Ok(timer::Instant::now()?.as_duration().as_millis() as u64)
+}
+
+// Copied on every access, so no references to the mutable static are created.
+static mut LOGGER: ConsoleLogger = ConsoleLogger::new(LevelFilter::Off);
+
+/// Registered with the `log` crate. Delegates to the logger passed to [init()].
+struct GlobalLogger;
+
+impl Log for GlobalLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        unsafe { LOGGER }.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        unsafe { LOGGER }.log(record)
+    }
+
+    fn flush(&self) {}
+}
+
+/// Registers the logger with the `log` crate. Fails if a logger has been registered already.
+pub fn init(logger: ConsoleLogger) -> TockResult<()> {
+    static GLOBAL_LOGGER: GlobalLogger = GlobalLogger;
+    static mut INITIALIZED: bool = false;
+
+    // Apps are single-threaded, so the logger cannot be registered concurrently.
+    unsafe {
+        if INITIALIZED {
+            return Err(OtherError::LoggerAlreadyInitialized.into());
+        }
+        INITIALIZED = true;
+        LOGGER = logger;
+        log::set_logger_racy(&GLOBAL_LOGGER).map_err(|_| OtherError::LoggerAlreadyInitialized)?;
+    }
+    log::set_max_level(logger.max_level);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::console::fake::FakeConsole;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use crate::syscalls::CommandReturn;
+    use log::Level;
+    use std::string::String;
+
+    fn log_to_console(logger: ConsoleLogger, level: Level) -> String {
+        let fake_console = FakeConsole::default();
+        let output = fake_console.output.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(crate::console::fake::DRIVER_NUMBER, fake_console);
+        // A timer running at 2 kHz, 12.5 s after start.
+        kernel.add_driver(
+            0,
+            |_: &mut DriverHandle, command_number, _, _| match command_number {
+                1 => CommandReturn::SuccessU32(2000),
+                _ => CommandReturn::SuccessU32(25_000),
+            },
+        );
+
+        kernel.run(|| {
+            logger.log(
+                &Record::builder()
+                    .level(level)
+                    .target("app")
+                    .args(format_args!("{} sensors found", 3))
+                    .build(),
+            )
+        });
+
+        let output = output.borrow().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    pub fn records_are_written_to_the_console() {
+        let logger = ConsoleLogger::new(LevelFilter::Info);
+        assert_eq!(
+            log_to_console(logger, Level::Warn),
+            "WARN  [app] 3 sensors found\n"
+        );
+        assert_eq!(log_to_console(logger, Level::Debug), "");
+    }
+
+    #[test]
+    pub fn records_can_be_prefixed_with_the_clock_value() {
+        let logger = ConsoleLogger::new(LevelFilter::Trace).with_timestamps();
+        assert_eq!(
+            log_to_console(logger, Level::Error),
+            "[  12.500] ERROR [app] 3 sensors found\n"
+        );
+    }
+}
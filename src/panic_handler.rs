@@ -0,0 +1,21 @@
+//! Panic handler printing the panic message and its location to the console. Errors returned
+//! from `main` are reported the same way. Enable it with the `console_panic_handler` feature.
+
+use crate::drivers;
+use core::fmt::Write;
+use core::panic::PanicInfo;
+use libtock_core::syscalls;
+
+static mut PANICKED: bool = false;
+
+#[panic_handler]
+unsafe fn panic_handler(info: &PanicInfo) -> ! {
+    // Do not try printing again if printing the message panicked itself.
+    if !PANICKED {
+        PANICKED = true;
+        // The console does not hold any state, so it can be used even if the app owns the drivers.
+        let mut console = drivers::retrieve_drivers_unsafe().console.create_console();
+        let _ = writeln!(console, "{}", info);
+    }
+    syscalls::exit(1)
+}
@@ -13,10 +13,34 @@ pub enum TockError {
     Other(OtherError),
 }
 
-#[cfg(not(any(target_arch = "arm", target_arch = "riscv32")))]
-impl core::fmt::Debug for TockError {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        writeln!(f, "impl Debug only for test builds")
+impl fmt::Display for TockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TockError::Subscribe(error) => fmt::Display::fmt(error, f),
+            TockError::Command(error) => fmt::Display::fmt(error, f),
+            TockError::Allow(error) => fmt::Display::fmt(error, f),
+            TockError::Format => f.write_str("formatting failed"),
+            TockError::Other(error) => fmt::Display::fmt(error, f),
+        }
+    }
+}
+
+// Implemented by hand to keep the formatting code small on the device.
+impl fmt::Debug for TockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl TockError {
+    /// The error code returned by the kernel, if the error originates from a system call.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            TockError::Subscribe(error) => Some(error.error_code),
+            TockError::Command(error) => Some(error.error_code),
+            TockError::Allow(error) => Some(error.error_code),
+            TockError::Format | TockError::Other(_) => None,
+        }
     }
 }
 
@@ -50,8 +74,51 @@ pub enum OtherError {
     GpioDriverInvalidState,
     TimerDriverDurationOutOfRange,
     TimerDriverErroneousClockFrequency,
+    TimerDriverErroneousCounterWidth,
     DriversAlreadyTaken,
     OutOfRange,
+    ExecutorTaskSlotsExhausted,
+    TimedOut,
+    ConsoleDriverLineTooLong,
+    ConsoleDriverBusy,
+    LoggerAlreadyInitialized,
+    ShellCommandSlotsExhausted,
+    TimerServiceSlotsExhausted,
+    I2cDriverNak,
+    I2cDriverInvalidAddress,
+    I2cDriverTransferTooLong,
+    SpiDriverBufferLengthMismatch,
+}
+
+impl fmt::Display for OtherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OtherError::ButtonsDriverInvalidState => "buttons driver in invalid state",
+            OtherError::GpioDriverInvalidState => "GPIO driver in invalid state",
+            OtherError::TimerDriverDurationOutOfRange => "timer duration out of range",
+            OtherError::TimerDriverErroneousClockFrequency => "erroneous timer clock frequency",
+            OtherError::TimerDriverErroneousCounterWidth => "erroneous timer counter width",
+            OtherError::DriversAlreadyTaken => "drivers already taken",
+            OtherError::OutOfRange => "out of range",
+            OtherError::ExecutorTaskSlotsExhausted => "no free task slot in executor",
+            OtherError::TimedOut => "timed out",
+            OtherError::ConsoleDriverLineTooLong => "console line too long",
+            OtherError::ConsoleDriverBusy => "console busy",
+            OtherError::LoggerAlreadyInitialized => "logger already initialized",
+            OtherError::ShellCommandSlotsExhausted => "no free command slot in shell",
+            OtherError::TimerServiceSlotsExhausted => "no free timer slot in timer service",
+            OtherError::I2cDriverNak => "I2C transfer not acknowledged",
+            OtherError::I2cDriverInvalidAddress => "invalid I2C address",
+            OtherError::I2cDriverTransferTooLong => "I2C transfer too long",
+            OtherError::SpiDriverBufferLengthMismatch => "SPI buffers differ in length",
+        })
+    }
+}
+
+impl fmt::Debug for OtherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
 }
 
 impl From<OtherError> for TockError {
@@ -1,5 +1,9 @@
 use crate::callback::Identity0Consumer;
+#[cfg(feature = "embedded-hal")]
+use crate::executor;
 use crate::futures;
+#[cfg(feature = "embedded-hal")]
+use crate::result::TockError;
 use crate::result::TockResult;
 use crate::syscalls;
 use core::cell::Cell;
@@ -34,9 +38,57 @@ impl RngDriver {
             &mut is_filled_alarm,
         )?;
         syscalls::command(DRIVER_NUMBER, command_nr::REQUEST_RNG, buf_len, 0)?;
-        futures::wait_until(|| is_filled.get()).await;
+        futures::wait_until_on_upcall(DRIVER_NUMBER, subscribe_nr::BUFFER_FILLED, || {
+            is_filled.get()
+        })
+        .await;
         mem::drop(subscription);
         mem::drop(shared_memory);
         Ok(())
     }
 }
+
+/// Blocks until the buffer is filled with random bytes.
+#[cfg(feature = "embedded-hal")]
+impl embedded_hal::blocking::rng::Read for RngDriver {
+    type Error = TockError;
+
+    fn read(&mut self, buffer: &mut [u8]) -> TockResult<()> {
+        unsafe { executor::block_on(self.fill_buffer(buffer)) }
+    }
+}
+
+#[cfg(all(test, feature = "embedded-hal"))]
+mod test {
+    use super::*;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use crate::syscalls::CommandReturn;
+    use embedded_hal::blocking::rng::Read;
+
+    #[test]
+    pub fn read_blocks_until_the_buffer_is_filled() {
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(
+            DRIVER_NUMBER,
+            |handle: &mut DriverHandle, command_number, len, _| {
+                assert_eq!(command_number, command_nr::REQUEST_RNG);
+                let buffer = handle.allowed_buffer(allow_nr::SHARE_BUFFER).unwrap();
+                for (index, byte) in buffer[..len].iter_mut().enumerate() {
+                    *byte = 0xa0 + index as u8;
+                }
+                handle.schedule_upcall(subscribe_nr::BUFFER_FILLED, (len, 0, 0));
+                CommandReturn::Success
+            },
+        );
+
+        let buffer = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut buffer = [0; 4];
+            drivers.rng.read(&mut buffer).unwrap();
+            buffer
+        });
+
+        assert_eq!(buffer, [0xa0, 0xa1, 0xa2, 0xa3]);
+    }
+}
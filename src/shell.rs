@@ -0,0 +1,397 @@
+//! Interactive command shell over the console. Commands are registered with a name, a help text
+//! and a handler. The shell echoes the input, supports backspace, Ctrl-C and browsing the history
+//! using the arrow keys, and splits lines into arguments at whitespace. Arguments containing
+//! whitespace can be enclosed in double quotes. The command `help` lists the registered commands.
+//!
+//! ```no_run
+//! # use libtock::console::Console;
+//! # use libtock::result::TockResult;
+//! # use libtock::shell::Shell;
+//! # use core::fmt::Write;
+//! # async fn doc() -> TockResult<()> {
+//! # let drivers = libtock::retrieve_drivers()?;
+//! let echo = |console: &mut Console, args: &[&str]| {
+//!     for arg in args {
+//!         write!(console, "{} ", arg)?;
+//!     }
+//!     write!(console, "\r\n")?;
+//!     Ok(())
+//! };
+//! let mut shell = Shell::new(drivers.console.create_console(), "> ");
+//! shell.register("echo", "Prints the arguments", &echo)?;
+//! shell.run().await
+//! # }
+//! ```
+
+use crate::console::Console;
+use crate::result::OtherError;
+use crate::result::TockResult;
+use core::fmt::Write;
+use core::str;
+
+/// Number of commands which can be registered in addition to `help`.
+pub const MAX_COMMANDS: usize = 16;
+
+/// Maximum length of an input line in bytes. Further input is ignored.
+pub const MAX_LINE_LEN: usize = 64;
+
+/// Maximum number of arguments, including the command name.
+pub const MAX_ARGS: usize = 8;
+
+/// Number of lines kept in the history.
+pub const HISTORY_LEN: usize = 4;
+
+const HELP_NAME: &str = "help";
+const HELP_TEXT: &str = "Lists the commands";
+
+const CTRL_C: u8 = 0x03;
+const BACKSPACE: u8 = 0x08;
+const ESCAPE: u8 = 0x1b;
+const DELETE: u8 = 0x7f;
+
+/// Handles a command. The arguments do not include the command name. Errors are printed to the
+/// console.
+pub type Handler<'a> = &'a dyn Fn(&mut Console, &[&str]) -> TockResult<()>;
+
+#[derive(Copy, Clone)]
+struct Command<'a> {
+    name: &'a str,
+    help: &'a str,
+    handler: Handler<'a>,
+}
+
+#[derive(Copy, Clone)]
+struct Line {
+    bytes: [u8; MAX_LINE_LEN],
+    len: usize,
+}
+
+impl Default for Line {
+    fn default() -> Self {
+        Line {
+            bytes: [0; MAX_LINE_LEN],
+            len: 0,
+        }
+    }
+}
+
+impl Line {
+    fn as_str(&self) -> &str {
+        // Only printable ASCII characters are added to lines.
+        str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+#[derive(Default)]
+struct History {
+    lines: [Line; HISTORY_LEN],
+    // Index of the most recent line.
+    newest: usize,
+    len: usize,
+}
+
+impl History {
+    fn push(&mut self, line: &Line) {
+        if line.len == 0 || (self.len > 0 && self.get(0).as_str() == line.as_str()) {
+            return;
+        }
+        self.newest = (self.newest + 1) % HISTORY_LEN;
+        self.lines[self.newest] = *line;
+        self.len = (self.len + 1).min(HISTORY_LEN);
+    }
+
+    /// Returns the line entered `age` lines ago, where 0 is the most recent one.
+    fn get(&self, age: usize) -> &Line {
+        &self.lines[(self.newest + HISTORY_LEN - age) % HISTORY_LEN]
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Escape {
+    None,
+    Started,
+    ControlSequence,
+}
+
+pub struct Shell<'a> {
+    console: Console,
+    prompt: &'a str,
+    commands: [Option<Command<'a>>; MAX_COMMANDS],
+    line: Line,
+    history: History,
+    // Terminals may end lines with "\r\n", which must not result in an additional empty line.
+    skip_line_feed: bool,
+}
+
+impl<'a> Shell<'a> {
+    pub fn new(console: Console, prompt: &'a str) -> Shell<'a> {
+        Shell {
+            console,
+            prompt,
+            commands: Default::default(),
+            line: Line::default(),
+            history: History::default(),
+            skip_line_feed: false,
+        }
+    }
+
+    /// Registers a command. Fails if [MAX_COMMANDS] commands have been registered already.
+    pub fn register(
+        &mut self,
+        name: &'a str,
+        help: &'a str,
+        handler: Handler<'a>,
+    ) -> TockResult<()> {
+        let free_slot = self
+            .commands
+            .iter_mut()
+            .find(|command| command.is_none())
+            .ok_or(OtherError::ShellCommandSlotsExhausted)?;
+        *free_slot = Some(Command {
+            name,
+            help,
+            handler,
+        });
+        Ok(())
+    }
+
+    /// Reads and executes commands until reading from or writing to the console fails.
+    pub async fn run(&mut self) -> TockResult<()> {
+        loop {
+            self.run_once().await?;
+        }
+    }
+
+    /// Shows the prompt, reads a line and executes it.
+    pub async fn run_once(&mut self) -> TockResult<()> {
+        self.console.write(self.prompt)?;
+        self.read_line().await?;
+        self.history.push(&self.line);
+        let line = self.line;
+        self.execute(line.as_str())
+    }
+
+    /// Executes the command line. Only errors writing to the console are returned.
+    pub fn execute(&mut self, line: &str) -> TockResult<()> {
+        let mut args = [""; MAX_ARGS];
+        let num_args = match tokenize(line, &mut args) {
+            Ok(num_args) => num_args,
+            Err(message) => return self.console.write(message),
+        };
+        if num_args == 0 {
+            return Ok(());
+        }
+        let name = args[0];
+        let args = &args[1..num_args];
+
+        if name == HELP_NAME {
+            return self.print_help();
+        }
+        let command = self
+            .commands
+            .iter()
+            .flatten()
+            .find(|command| command.name == name);
+        match command {
+            Some(command) => {
+                if let Err(error) = (command.handler)(&mut self.console, args) {
+                    write!(self.console, "{}: {}\r\n", name, error)?;
+                }
+                Ok(())
+            }
+            None => {
+                write!(self.console, "{}: command not found\r\n", name)?;
+                Ok(())
+            }
+        }
+    }
+
+    fn print_help(&mut self) -> TockResult<()> {
+        for command in self.commands.iter().flatten() {
+            write!(self.console, "{:12} {}\r\n", command.name, command.help)?;
+        }
+        write!(self.console, "{:12} {}\r\n", HELP_NAME, HELP_TEXT)?;
+        Ok(())
+    }
+
+    async fn read_line(&mut self) -> TockResult<()> {
+        self.line.len = 0;
+        let mut escape = Escape::None;
+        // Age of the history line shown, if any.
+        let mut history_age: Option<usize> = None;
+        loop {
+            let mut byte = [0];
+            self.console.read_exact(&mut byte).await?;
+            let byte = byte[0];
+            let skip_line_feed = self.skip_line_feed;
+            self.skip_line_feed = false;
+
+            match (escape, byte) {
+                (Escape::None, ESCAPE) => escape = Escape::Started,
+                (Escape::Started, b'[') => escape = Escape::ControlSequence,
+                (Escape::ControlSequence, b'A') => {
+                    escape = Escape::None;
+                    let age = history_age.map_or(0, |age| age + 1);
+                    if age < self.history.len {
+                        history_age = Some(age);
+                        self.line = *self.history.get(age);
+                        self.redraw_line()?;
+                    }
+                }
+                (Escape::ControlSequence, b'B') => {
+                    escape = Escape::None;
+                    match history_age {
+                        Some(0) => {
+                            history_age = None;
+                            self.line.len = 0;
+                            self.redraw_line()?;
+                        }
+                        Some(age) => {
+                            history_age = Some(age - 1);
+                            self.line = *self.history.get(age - 1);
+                            self.redraw_line()?;
+                        }
+                        None => {}
+                    }
+                }
+                // Other escape sequences, e.g. the left and right arrow keys, are not supported.
+                (Escape::Started, _) | (Escape::ControlSequence, _) => escape = Escape::None,
+                (Escape::None, b'\n') if skip_line_feed => {}
+                (Escape::None, b'\r') | (Escape::None, b'\n') => {
+                    self.skip_line_feed = byte == b'\r';
+                    self.console.write("\r\n")?;
+                    return Ok(());
+                }
+                (Escape::None, CTRL_C) => {
+                    self.line.len = 0;
+                    self.console.write("^C\r\n")?;
+                    return Ok(());
+                }
+                (Escape::None, BACKSPACE) | (Escape::None, DELETE) => {
+                    if self.line.len > 0 {
+                        self.line.len -= 1;
+                        self.console.write("\x08 \x08")?;
+                    }
+                }
+                (Escape::None, b' '..=b'~') => {
+                    if self.line.len < MAX_LINE_LEN {
+                        self.line.bytes[self.line.len] = byte;
+                        self.line.len += 1;
+                        self.console.write([byte])?;
+                    }
+                }
+                (Escape::None, _) => {}
+            }
+        }
+    }
+
+    fn redraw_line(&mut self) -> TockResult<()> {
+        // Return to the start of the line and clear it.
+        self.console.write("\r\x1b[K")?;
+        self.console.write(self.prompt)?;
+        self.console.write(&self.line.bytes[..self.line.len])
+    }
+}
+
+/// Splits the line into arguments at whitespace. Returns the number of arguments or an error
+/// message.
+fn tokenize<'l>(line: &'l str, args: &mut [&'l str]) -> Result<usize, &'static str> {
+    let mut num_args = 0;
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let (arg, remainder) = if rest.as_bytes()[0] == b'"' {
+            let quoted = &rest[1..];
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => return Err("unterminated quote\r\n"),
+            }
+        } else {
+            let end = rest
+                .find(|c: char| c.is_ascii_whitespace())
+                .unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        };
+        if num_args == args.len() {
+            return Err("too many arguments\r\n");
+        }
+        args[num_args] = arg;
+        num_args += 1;
+        rest = remainder.trim_start();
+    }
+    Ok(num_args)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::console::fake::FakeConsole;
+    use crate::console::fake::DRIVER_NUMBER;
+    use crate::executor;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use std::cell::RefCell;
+    use std::string::String;
+    use std::vec::Vec;
+
+    /// Runs the shell on the input and returns the arguments passed to the `echo` command and
+    /// the output.
+    fn run_shell(input: &[u8], num_lines: usize) -> (Vec<Vec<String>>, String) {
+        let fake_console = FakeConsole::with_input(input);
+        let output = fake_console.output.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, fake_console);
+
+        let echoed = RefCell::new(Vec::new());
+        kernel.run(|| {
+            let drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let echo = |_: &mut Console, args: &[&str]| {
+                let args = args.iter().map(|arg| String::from(*arg)).collect();
+                echoed.borrow_mut().push(args);
+                Ok(())
+            };
+            let fail = |_: &mut Console, _: &[&str]| Err(OtherError::OutOfRange.into());
+            let mut shell = Shell::new(drivers.console.create_console(), "> ");
+            shell
+                .register("echo", "Records the arguments", &echo)
+                .unwrap();
+            shell.register("fail", "Always fails", &fail).unwrap();
+            for _ in 0..num_lines {
+                unsafe { executor::block_on(shell.run_once()) }.unwrap();
+            }
+        });
+
+        let output = String::from_utf8(output.borrow().clone()).unwrap();
+        (echoed.into_inner(), output)
+    }
+
+    #[test]
+    pub fn arguments_are_split_at_whitespace_and_quotes() {
+        let mut args = [""; MAX_ARGS];
+        let num_args = tokenize("  set  name \"Tock OS\" x", &mut args).unwrap();
+        assert_eq!(args[..num_args], ["set", "name", "Tock OS", "x"]);
+        assert!(tokenize("echo \"unterminated", &mut args).is_err());
+        assert!(tokenize("a b c d e f g h i", &mut args).is_err());
+    }
+
+    #[test]
+    pub fn commands_are_executed_and_input_is_echoed() {
+        let (echoed, output) = run_shell(b"echo a b\r\nunknown\r\nfail\n", 3);
+        assert_eq!(echoed, vec![vec!["a", "b"]]);
+        assert_eq!(
+            output,
+            "> echo a b\r\n> unknown\r\nunknown: command not found\r\n> fail\r\nfail: out of range\r\n"
+        );
+    }
+
+    #[test]
+    pub fn lines_can_be_edited() {
+        let (echoed, _) = run_shell(b"echo ab\x7fc\r\nx\x03echo d\r", 3);
+        assert_eq!(echoed, vec![vec!["ac"], vec!["d"]]);
+    }
+
+    #[test]
+    pub fn previous_lines_can_be_recalled() {
+        let (echoed, output) = run_shell(b"echo 1\recho 2\r\x1b[A\x1b[A\r\x1b[A\x1b[A\x1b[B\r", 4);
+        assert_eq!(echoed, vec![vec!["1"], vec!["2"], vec!["1"], vec!["1"]]);
+        assert!(output.contains("\r\x1b[K> echo 2\r\x1b[K> echo 1"));
+    }
+}
@@ -180,14 +180,18 @@ pub struct BleScanningDriverScanning<'a> {
 impl<'a> BleScanningDriverScanning<'a> {
     /// Create stream of ble scanning packets
     pub fn stream_values(&'a self) -> impl Future<Output = ScanBuffer> + 'a {
-        crate::futures::wait_for_value(move || {
-            if let Some(temp_buffer) = self.read_value.get() {
-                self.read_value.set(None);
-                Some(temp_buffer)
-            } else {
-                None
-            }
-        })
+        crate::futures::wait_for_value_on_upcall(
+            DRIVER_NUMBER,
+            subscribe_nr::BLE_PASSIVE_SCAN_SUB,
+            move || {
+                if let Some(temp_buffer) = self.read_value.get() {
+                    self.read_value.set(None);
+                    Some(temp_buffer)
+                } else {
+                    None
+                }
+            },
+        )
     }
 }
 
@@ -0,0 +1,289 @@
+use crate::callback::Identity1Consumer;
+use crate::futures;
+use crate::result::OtherError;
+use crate::result::TockResult;
+use crate::syscalls;
+use core::cell::Cell;
+use core::marker::PhantomData;
+use core::mem;
+
+const DRIVER_NUMBER: usize = 0x20001;
+
+mod command_nr {
+    pub const CHECK_PRESENT: usize = 0;
+    pub const READ_WRITE: usize = 2;
+    pub const SET_CHIP_SELECT: usize = 3;
+    pub const SET_RATE: usize = 5;
+    pub const GET_RATE: usize = 6;
+    pub const SET_PHASE: usize = 7;
+    pub const SET_POLARITY: usize = 9;
+}
+
+mod subscribe_nr {
+    pub const READ_WRITE_DONE: usize = 0;
+}
+
+mod allow_nr {
+    pub const WRITE_BUFFER: usize = 0;
+    pub const READ_BUFFER: usize = 0;
+}
+
+#[non_exhaustive]
+pub struct SpiDriverFactory;
+
+impl SpiDriverFactory {
+    pub fn init_driver(&mut self) -> TockResult<SpiController> {
+        syscalls::command(DRIVER_NUMBER, command_nr::CHECK_PRESENT, 0, 0)?;
+        let spi_controller = SpiController {
+            lifetime: PhantomData,
+        };
+        Ok(spi_controller)
+    }
+}
+
+/// Transfers data to and from the selected device on the SPI bus using the SPI controller
+/// capsule. The data to write is shared read-only with the kernel and the received data is
+/// written into a separate buffer, so neither is copied.
+///
+/// If the future of a transfer is dropped before the transfer has completed, the capsule remains
+/// busy until the transfer is over.
+pub struct SpiController<'a> {
+    lifetime: PhantomData<&'a ()>,
+}
+
+impl<'a> SpiController<'a> {
+    /// Select the device the following transfers are addressed to by the index of its chip
+    /// select line.
+    pub fn select_chip(&mut self, chip_select: usize) -> TockResult<()> {
+        syscalls::command(DRIVER_NUMBER, command_nr::SET_CHIP_SELECT, chip_select, 0)?;
+        Ok(())
+    }
+
+    /// Set the clock rate in Hz. The kernel chooses the closest rate supported by the hardware,
+    /// which is returned by [rate](SpiController::rate).
+    pub fn set_rate(&mut self, rate: u32) -> TockResult<()> {
+        syscalls::command(DRIVER_NUMBER, command_nr::SET_RATE, rate as usize, 0)?;
+        Ok(())
+    }
+
+    /// The clock rate in Hz.
+    pub fn rate(&self) -> TockResult<u32> {
+        let rate = syscalls::command_u32(DRIVER_NUMBER, command_nr::GET_RATE, 0, 0)?;
+        Ok(rate)
+    }
+
+    pub fn set_polarity(&mut self, polarity: Polarity) -> TockResult<()> {
+        syscalls::command(
+            DRIVER_NUMBER,
+            command_nr::SET_POLARITY,
+            polarity as usize,
+            0,
+        )?;
+        Ok(())
+    }
+
+    pub fn set_phase(&mut self, phase: Phase) -> TockResult<()> {
+        syscalls::command(DRIVER_NUMBER, command_nr::SET_PHASE, phase as usize, 0)?;
+        Ok(())
+    }
+
+    /// Write the data to the selected device, discarding the received bytes.
+    pub async fn write(&mut self, data: &[u8]) -> TockResult<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let write_buffer = syscalls::allow_readonly(DRIVER_NUMBER, allow_nr::WRITE_BUFFER, data)?;
+        self.transfer(data.len()).await?;
+        mem::drop(write_buffer);
+        Ok(())
+    }
+
+    /// Write the data to the selected device and receive the same number of bytes into the
+    /// buffer at the same time. Fails with `OtherError::SpiDriverBufferLengthMismatch` unless
+    /// both have the same length.
+    pub async fn read_write(&mut self, data: &[u8], buffer: &mut [u8]) -> TockResult<()> {
+        if data.len() != buffer.len() {
+            return Err(OtherError::SpiDriverBufferLengthMismatch.into());
+        }
+        if data.is_empty() {
+            return Ok(());
+        }
+        let write_buffer = syscalls::allow_readonly(DRIVER_NUMBER, allow_nr::WRITE_BUFFER, data)?;
+        let read_buffer = syscalls::allow(DRIVER_NUMBER, allow_nr::READ_BUFFER, buffer)?;
+        self.transfer(data.len()).await?;
+        mem::drop(read_buffer);
+        mem::drop(write_buffer);
+        Ok(())
+    }
+
+    async fn transfer(&mut self, len: usize) -> TockResult<()> {
+        let is_done = Cell::new(false);
+        let mut transfer_done = |_| is_done.set(true);
+        let subscription = syscalls::subscribe::<Identity1Consumer, _>(
+            DRIVER_NUMBER,
+            subscribe_nr::READ_WRITE_DONE,
+            &mut transfer_done,
+        )?;
+        syscalls::command(DRIVER_NUMBER, command_nr::READ_WRITE, len, 0)?;
+        futures::wait_until_on_upcall(DRIVER_NUMBER, subscribe_nr::READ_WRITE_DONE, || {
+            is_done.get()
+        })
+        .await;
+        mem::drop(subscription);
+        Ok(())
+    }
+}
+
+/// The level of the clock line while the bus is idle.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Polarity {
+    IdleLow = 0,
+    IdleHigh = 1,
+}
+
+/// The clock edge on which data is sampled.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Phase {
+    SampleLeading = 0,
+    SampleTrailing = 1,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::executor;
+    use crate::result::ErrorCode;
+    use crate::result::TockError;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeDriver;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use crate::syscalls::CommandReturn;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Answers every byte with its complement and records the configuration.
+    #[derive(Default)]
+    struct FakeDevice {
+        written: Rc<RefCell<Vec<u8>>>,
+        configuration: Rc<RefCell<Vec<(usize, usize)>>>,
+        rate: u32,
+    }
+
+    impl FakeDriver for FakeDevice {
+        fn command(
+            &mut self,
+            handle: &mut DriverHandle,
+            command_number: usize,
+            arg1: usize,
+            _: usize,
+        ) -> CommandReturn {
+            match command_number {
+                command_nr::CHECK_PRESENT => {}
+                command_nr::READ_WRITE => {
+                    let data = handle
+                        .allowed_readonly_buffer(allow_nr::WRITE_BUFFER)
+                        .unwrap()[..arg1]
+                        .to_vec();
+                    if let Some(buffer) = handle.allowed_buffer(allow_nr::READ_BUFFER) {
+                        for (received, written) in buffer.iter_mut().zip(&data) {
+                            *received = !written;
+                        }
+                    }
+                    self.written.borrow_mut().extend(data);
+                    handle.schedule_upcall(subscribe_nr::READ_WRITE_DONE, (arg1, 0, 0));
+                }
+                command_nr::SET_RATE => {
+                    // The hardware only supports multiples of 1 kHz.
+                    self.rate = arg1 as u32 / 1000 * 1000;
+                }
+                command_nr::GET_RATE => return CommandReturn::SuccessU32(self.rate),
+                command_nr::SET_CHIP_SELECT | command_nr::SET_POLARITY | command_nr::SET_PHASE => {
+                    self.configuration.borrow_mut().push((command_number, arg1));
+                }
+                _ => return CommandReturn::Failure(ErrorCode::NoSupport),
+            }
+            CommandReturn::Success
+        }
+    }
+
+    #[test]
+    pub fn written_data_is_shared_without_copying() {
+        let device = FakeDevice::default();
+        let written = device.written.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, device);
+
+        kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut spi = drivers.spi.init_driver().unwrap();
+            unsafe { executor::block_on(spi.write(b"\x01\x02\x03")) }.unwrap();
+        });
+
+        assert_eq!(*written.borrow(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    pub fn received_bytes_are_written_into_a_separate_buffer() {
+        let device = FakeDevice::default();
+        let written = device.written.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, device);
+
+        let received = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut spi = drivers.spi.init_driver().unwrap();
+            let mut buffer = [0; 2];
+            unsafe { executor::block_on(spi.read_write(&[0x0f, 0xf0], &mut buffer)) }.unwrap();
+            buffer
+        });
+
+        assert_eq!(*written.borrow(), vec![0x0f, 0xf0]);
+        assert_eq!(received, [0xf0, 0x0f]);
+    }
+
+    #[test]
+    pub fn buffers_of_different_length_are_rejected() {
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, FakeDevice::default());
+
+        let result = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut spi = drivers.spi.init_driver().unwrap();
+            let mut buffer = [0; 1];
+            unsafe { executor::block_on(spi.read_write(&[1, 2], &mut buffer)) }
+        });
+
+        match result {
+            Err(TockError::Other(OtherError::SpiDriverBufferLengthMismatch)) => {}
+            other => panic!("Expected a length mismatch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn configuration_is_passed_to_the_capsule() {
+        let device = FakeDevice::default();
+        let configuration = device.configuration.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, device);
+
+        let rate = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut spi = drivers.spi.init_driver().unwrap();
+            spi.select_chip(2).unwrap();
+            spi.set_polarity(Polarity::IdleHigh).unwrap();
+            spi.set_phase(Phase::SampleTrailing).unwrap();
+            spi.set_rate(400_500).unwrap();
+            spi.rate().unwrap()
+        });
+
+        assert_eq!(rate, 400_000);
+        assert_eq!(
+            *configuration.borrow(),
+            vec![
+                (command_nr::SET_CHIP_SELECT, 2),
+                (command_nr::SET_POLARITY, 1),
+                (command_nr::SET_PHASE, 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,513 @@
+//! Async synchronization primitives for futures running on the same executor.
+//!
+//! Apps are single-threaded and upcalls are only delivered while the executor yields, i.e. never
+//! while a future is polled. Hence, the primitives do not need atomics and the non-blocking
+//! operations ([Signal::signal], [Channel::try_send], [Semaphore::release]) can be used from
+//! [Consumer::consume](crate::callback::Consumer::consume). Waiting futures are woken using
+//! their wakers.
+
+use core::cell::Cell;
+use core::cell::RefCell;
+use core::cell::UnsafeCell;
+use core::mem;
+use core::ops::Deref;
+use core::ops::DerefMut;
+use core::task::Poll;
+use core::task::Waker;
+use futures::future;
+
+/// Number of futures which can wait for a primitive at the same time. If more futures wait, the
+/// surplus ones are polled again after the next yield.
+pub const MAX_WAITERS: usize = 4;
+
+#[derive(Default)]
+struct WaitQueue {
+    wakers: RefCell<[Option<Waker>; MAX_WAITERS]>,
+}
+
+impl WaitQueue {
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.wakers.borrow_mut();
+        let already_registered = wakers.iter().any(|entry| match entry {
+            Some(registered) => registered.will_wake(waker),
+            None => false,
+        });
+        if already_registered {
+            return;
+        }
+        match wakers.iter_mut().find(|entry| entry.is_none()) {
+            Some(entry) => *entry = Some(waker.clone()),
+            None => waker.wake_by_ref(),
+        }
+    }
+
+    // Waiters whose condition still does not hold register again when they are polled.
+    fn wake_all(&self) {
+        let mut wakers = mem::take(&mut *self.wakers.borrow_mut());
+        for waker in wakers.iter_mut().filter_map(Option::take) {
+            waker.wake();
+        }
+    }
+}
+
+/// Passes a value to the futures waiting for it, e.g. from an upcall to a task. Signalling again
+/// before the value has been taken overwrites the value. Use `Signal<()>` as an event flag.
+///
+/// ```no_run
+/// # use libtock::callback::Identity1Consumer;
+/// # use libtock::result::TockResult;
+/// # use libtock::sync::Signal;
+/// # use libtock::syscalls;
+/// # async fn doc() -> TockResult<()> {
+/// let signal = Signal::new();
+/// let mut callback = |value| signal.signal(value);
+/// let _subscription = syscalls::subscribe::<Identity1Consumer, _>(0x3, 0, &mut callback)?;
+/// let value = signal.wait().await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Signal<T> {
+    value: Cell<Option<T>>,
+    waiters: WaitQueue,
+}
+
+impl<T> Default for Signal<T> {
+    fn default() -> Self {
+        Signal::new()
+    }
+}
+
+impl<T> Signal<T> {
+    pub fn new() -> Signal<T> {
+        Signal {
+            value: Cell::new(None),
+            waiters: WaitQueue::default(),
+        }
+    }
+
+    /// Stores the value and wakes the waiting futures.
+    pub fn signal(&self, value: T) {
+        self.value.set(Some(value));
+        self.waiters.wake_all();
+    }
+
+    /// Takes the value without waiting.
+    pub fn try_take(&self) -> Option<T> {
+        self.value.take()
+    }
+
+    pub fn is_signaled(&self) -> bool {
+        let value = self.value.take();
+        let signaled = value.is_some();
+        self.value.set(value);
+        signaled
+    }
+
+    /// Waits until a value has been signalled and takes it. If several futures wait, only one of
+    /// them receives the value.
+    pub async fn wait(&self) -> T {
+        future::poll_fn(|cx| match self.value.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                self.waiters.register(cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// Bounded first-in first-out queue. The caller provides the storage, so the capacity is the
+/// length of the buffer.
+///
+/// ```no_run
+/// # use libtock::sync::Channel;
+/// # async fn doc() {
+/// let mut buffer = [None, None, None, None];
+/// let channel = Channel::new(&mut buffer);
+/// channel.send(42).await;
+/// assert_eq!(channel.receive().await, 42);
+/// # }
+/// ```
+pub struct Channel<'a, T> {
+    buffer: RefCell<&'a mut [Option<T>]>,
+    head: Cell<usize>,
+    len: Cell<usize>,
+    senders: WaitQueue,
+    receivers: WaitQueue,
+}
+
+impl<'a, T> Channel<'a, T> {
+    pub fn new(buffer: &'a mut [Option<T>]) -> Channel<'a, T> {
+        for entry in buffer.iter_mut() {
+            *entry = None;
+        }
+        Channel {
+            buffer: RefCell::new(buffer),
+            head: Cell::new(0),
+            len: Cell::new(0),
+            senders: WaitQueue::default(),
+            receivers: WaitQueue::default(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.buffer.borrow().len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len.get() == 0
+    }
+
+    /// Enqueues the value without waiting. Returns the value if the channel is full.
+    pub fn try_send(&self, value: T) -> Result<(), T> {
+        let capacity = self.capacity();
+        if self.len.get() == capacity {
+            return Err(value);
+        }
+        let tail = (self.head.get() + self.len.get()) % capacity;
+        self.buffer.borrow_mut()[tail] = Some(value);
+        self.len.set(self.len.get() + 1);
+        self.receivers.wake_all();
+        Ok(())
+    }
+
+    /// Dequeues the oldest value without waiting.
+    pub fn try_receive(&self) -> Option<T> {
+        if self.len.get() == 0 {
+            return None;
+        }
+        let head = self.head.get();
+        let value = self.buffer.borrow_mut()[head].take();
+        self.head.set((head + 1) % self.capacity());
+        self.len.set(self.len.get() - 1);
+        self.senders.wake_all();
+        value
+    }
+
+    /// Waits until the channel has room for the value and enqueues it.
+    pub async fn send(&self, value: T) {
+        let mut value = Some(value);
+        future::poll_fn(|cx| {
+            let value_to_send = value.take().expect("Value already sent");
+            match self.try_send(value_to_send) {
+                Ok(()) => Poll::Ready(()),
+                Err(value_to_send) => {
+                    value = Some(value_to_send);
+                    self.senders.register(cx.waker());
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    /// Waits until the channel is not empty and dequeues the oldest value.
+    pub async fn receive(&self) -> T {
+        future::poll_fn(|cx| match self.try_receive() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                self.receivers.register(cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// Grants exclusive access to a value to one future at a time, also across `.await`s.
+///
+/// ```no_run
+/// # use libtock::sync::Mutex;
+/// # async fn doc() {
+/// let counter = Mutex::new(0);
+/// *counter.lock().await += 1;
+/// # }
+/// ```
+pub struct Mutex<T> {
+    locked: Cell<bool>,
+    value: UnsafeCell<T>,
+    waiters: WaitQueue,
+}
+
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Mutex<T> {
+        Mutex {
+            locked: Cell::new(false),
+            value: UnsafeCell::new(value),
+            waiters: WaitQueue::default(),
+        }
+    }
+
+    /// Locks the mutex if it is not locked already.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        if self.locked.get() {
+            return None;
+        }
+        self.locked.set(true);
+        Some(MutexGuard { mutex: self })
+    }
+
+    /// Waits until the mutex is unlocked and locks it.
+    pub async fn lock(&self) -> MutexGuard<'_, T> {
+        future::poll_fn(|cx| match self.try_lock() {
+            Some(guard) => Poll::Ready(guard),
+            None => {
+                self.waiters.register(cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+/// Unlocks the mutex when dropped.
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<'a, T> Deref for MutexGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // The guard is the only reference to the value while the mutex is locked.
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+
+impl<'a, T> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+
+impl<'a, T> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.mutex.locked.set(false);
+        self.mutex.waiters.wake_all();
+    }
+}
+
+/// Counting semaphore. Permits are returned when the [SemaphorePermit] is dropped. Permits can
+/// also be added by [Semaphore::release], e.g. once per upcall.
+///
+/// ```no_run
+/// # use libtock::sync::Semaphore;
+/// # async fn doc() {
+/// let semaphore = Semaphore::new(2);
+/// let permit = semaphore.acquire().await;
+/// // At most two futures get here at the same time.
+/// # }
+/// ```
+pub struct Semaphore {
+    permits: Cell<usize>,
+    waiters: WaitQueue,
+}
+
+impl Semaphore {
+    pub fn new(permits: usize) -> Semaphore {
+        Semaphore {
+            permits: Cell::new(permits),
+            waiters: WaitQueue::default(),
+        }
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.permits.get()
+    }
+
+    /// Adds permits and wakes the waiting futures.
+    pub fn release(&self, permits: usize) {
+        self.permits.set(self.permits.get() + permits);
+        self.waiters.wake_all();
+    }
+
+    /// Takes a permit if one is available.
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        let permits = self.permits.get();
+        if permits == 0 {
+            return None;
+        }
+        self.permits.set(permits - 1);
+        Some(SemaphorePermit { semaphore: self })
+    }
+
+    /// Waits until a permit is available and takes it.
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        future::poll_fn(|cx| match self.try_acquire() {
+            Some(permit) => Poll::Ready(permit),
+            None => {
+                self.waiters.register(cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// Returns the permit to its semaphore when dropped.
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl<'a> SemaphorePermit<'a> {
+    /// Consumes the permit without returning it to the semaphore.
+    pub fn forget(self) {
+        mem::forget(self);
+    }
+}
+
+impl<'a> Drop for SemaphorePermit<'a> {
+    fn drop(&mut self) {
+        self.semaphore.release(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::callback::Identity1Consumer;
+    use crate::executor::block_on;
+    use crate::executor::Executor;
+    use crate::executor::Task;
+    use crate::syscalls;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use crate::syscalls::CommandReturn;
+    use ::futures::pin_mut;
+
+    /// A future which is pending the first time it is polled.
+    async fn yield_once() {
+        let mut yielded = false;
+        future::poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    async fn hold_permit(semaphore: &Semaphore, holders: &Cell<usize>, max_holders: &Cell<usize>) {
+        let _permit = semaphore.acquire().await;
+        holders.set(holders.get() + 1);
+        max_holders.set(max_holders.get().max(holders.get()));
+        yield_once().await;
+        holders.set(holders.get() - 1);
+    }
+
+    #[test]
+    pub fn signals_from_upcalls_wake_the_waiting_future() {
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(0x10, |handle: &mut DriverHandle, _, value, _| {
+            handle.schedule_upcall(0, (value, 0, 0));
+            CommandReturn::Success
+        });
+
+        let value = kernel.run(|| {
+            let signal = Signal::new();
+            let mut callback = |value| signal.signal(value);
+            let _subscription =
+                syscalls::subscribe::<Identity1Consumer, _>(0x10, 0, &mut callback).unwrap();
+            syscalls::command(0x10, 0, 42, 0).unwrap();
+            unsafe { block_on(signal.wait()) }
+        });
+
+        assert_eq!(value, 42);
+    }
+
+    #[test]
+    pub fn senders_wait_until_the_channel_has_room() {
+        let mut buffer = [None, None];
+        let channel = Channel::new(&mut buffer);
+        let log = RefCell::new(Vec::new());
+        let producer = Task::new(async {
+            for value in 0..5 {
+                channel.send(value).await;
+                log.borrow_mut().push(("sent", value));
+            }
+        });
+        let consumer = Task::new(async {
+            for _ in 0..5 {
+                let value = channel.receive().await;
+                log.borrow_mut().push(("received", value));
+                yield_once().await;
+            }
+        });
+        pin_mut!(producer);
+        pin_mut!(consumer);
+
+        let executor = Executor::new();
+        executor.spawn(producer.as_ref()).unwrap();
+        executor.spawn(consumer.as_ref()).unwrap();
+        unsafe { block_on(executor.run()) };
+
+        let log = log.borrow();
+        let received: Vec<_> = log
+            .iter()
+            .filter(|(event, _)| *event == "received")
+            .map(|(_, value)| *value)
+            .collect();
+        assert_eq!(received, vec![0, 1, 2, 3, 4]);
+        // The third value is only sent after the first one has been received.
+        assert_eq!(log[..3], [("sent", 0), ("sent", 1), ("received", 0)]);
+        assert!(channel.is_empty());
+    }
+
+    #[test]
+    pub fn mutex_is_held_across_awaits() {
+        let mutex = Mutex::new(Vec::new());
+        let task_a = Task::new(async {
+            let mut guard = mutex.lock().await;
+            guard.push("a1");
+            yield_once().await;
+            guard.push("a2");
+        });
+        let task_b = Task::new(async {
+            mutex.lock().await.push("b");
+        });
+        pin_mut!(task_a);
+        pin_mut!(task_b);
+
+        let executor = Executor::new();
+        executor.spawn(task_a.as_ref()).unwrap();
+        executor.spawn(task_b.as_ref()).unwrap();
+        unsafe { block_on(executor.run()) };
+
+        assert_eq!(*mutex.try_lock().unwrap(), vec!["a1", "a2", "b"]);
+    }
+
+    #[test]
+    pub fn semaphore_limits_the_number_of_concurrent_holders() {
+        let semaphore = Semaphore::new(2);
+        let holders = Cell::new(0);
+        let max_holders = Cell::new(0);
+        let task_a = Task::new(hold_permit(&semaphore, &holders, &max_holders));
+        let task_b = Task::new(hold_permit(&semaphore, &holders, &max_holders));
+        let task_c = Task::new(hold_permit(&semaphore, &holders, &max_holders));
+        pin_mut!(task_a);
+        pin_mut!(task_b);
+        pin_mut!(task_c);
+
+        let executor = Executor::new();
+        let join_c = executor.spawn(task_c.as_ref()).unwrap();
+        executor.spawn(task_a.as_ref()).unwrap();
+        executor.spawn(task_b.as_ref()).unwrap();
+        unsafe { block_on(executor.run()) };
+
+        assert!(join_c.is_finished());
+        assert_eq!(max_holders.get(), 2);
+        assert_eq!(semaphore.available_permits(), 2);
+    }
+}
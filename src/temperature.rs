@@ -48,7 +48,12 @@ impl<'a> TemperatureDriver<'a> {
         )?;
         syscalls::command(DRIVER_NUMBER, command_nr::START_MEASUREMENT, 0, 0)?;
         let result = Temperature {
-            centi_celsius: futures::wait_for_value(|| temperature.get()).await,
+            centi_celsius: futures::wait_for_value_on_upcall(
+                DRIVER_NUMBER,
+                subscribe_nr::SUBSCRIBE_CALLBACK,
+                || temperature.get(),
+            )
+            .await,
         };
         mem::drop(subscription);
         Ok(result)
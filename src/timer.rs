@@ -2,16 +2,25 @@
 
 use crate::callback::CallbackSubscription;
 use crate::callback::Consumer;
+#[cfg(feature = "embedded-hal")]
+use crate::executor;
 use crate::futures;
+use crate::futures::Either;
+use crate::result::ErrorCode;
 use crate::result::OtherError;
 use crate::result::TockError;
 use crate::result::TockResult;
-use crate::result::EALREADY;
 use crate::syscalls;
 use core::cell::Cell;
+use core::future::Future;
 use core::isize;
 use core::marker::PhantomData;
+use core::mem;
 use core::ops::{Add, AddAssign, Sub};
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+use libtock_core::callback;
 
 const DRIVER_NUMBER: usize = 0x00000;
 
@@ -21,6 +30,7 @@ mod command_nr {
     pub const GET_CLOCK_VALUE: usize = 2;
     pub const STOP_ALARM: usize = 3;
     pub const SET_ALARM: usize = 4;
+    pub const GET_COUNTER_WIDTH: usize = 7;
 }
 
 mod subscribe_nr {
@@ -49,11 +59,10 @@ impl<CB: FnMut(ClockValue, Alarm)> Consumer<WithCallback<'_, CB>> for TimerEvent
 
 impl<'a, CB: FnMut(ClockValue, Alarm)> WithCallback<'a, CB> {
     pub fn init(&'a mut self) -> TockResult<Timer<'a>> {
-        let num_notifications =
-            syscalls::command(DRIVER_NUMBER, command_nr::IS_DRIVER_AVAILABLE, 0, 0)?;
+        syscalls::command(DRIVER_NUMBER, command_nr::IS_DRIVER_AVAILABLE, 0, 0)?;
 
         let clock_frequency =
-            syscalls::command(DRIVER_NUMBER, command_nr::GET_CLOCK_FREQUENCY, 0, 0)?;
+            syscalls::command_u32(DRIVER_NUMBER, command_nr::GET_CLOCK_FREQUENCY, 0, 0)? as usize;
 
         if clock_frequency == 0 {
             return Err(OtherError::TimerDriverErroneousClockFrequency.into());
@@ -70,7 +79,6 @@ impl<'a, CB: FnMut(ClockValue, Alarm)> WithCallback<'a, CB> {
         )?;
 
         Ok(Timer {
-            num_notifications,
             clock_frequency,
             subscription,
         })
@@ -78,25 +86,19 @@ impl<'a, CB: FnMut(ClockValue, Alarm)> WithCallback<'a, CB> {
 }
 
 pub struct Timer<'a> {
-    num_notifications: usize,
     clock_frequency: ClockFrequency,
     #[allow(dead_code)] // Used in drop
     subscription: CallbackSubscription<'a>,
 }
 
 impl<'a> Timer<'a> {
-    pub fn num_notifications(&self) -> usize {
-        self.num_notifications
-    }
-
     pub fn clock_frequency(&self) -> ClockFrequency {
         self.clock_frequency
     }
 
     pub fn get_current_clock(&self) -> TockResult<ClockValue> {
         Ok(ClockValue {
-            num_ticks: syscalls::command(DRIVER_NUMBER, command_nr::GET_CLOCK_VALUE, 0, 0)?
-                as isize,
+            num_ticks: get_current_ticks()? as isize,
             clock_frequency: self.clock_frequency,
         })
     }
@@ -106,31 +108,29 @@ impl<'a> Timer<'a> {
         Ok(())
     }
 
-    pub fn set_alarm(&mut self, duration: Duration<isize>) -> TockResult<Alarm> {
+    /// Sets an alarm after the given duration, which is either a [Duration] or a
+    /// `core::time::Duration`.
+    pub fn set_alarm<D: Into<core::time::Duration>>(&mut self, duration: D) -> TockResult<Alarm> {
         let now = self.get_current_clock()?;
-        let freq = self.clock_frequency.hz();
-        let duration_ms = duration.ms() as usize;
-        let ticks = match duration_ms.checked_mul(freq) {
-            Some(x) => x / 1000,
-            None => {
-                // Divide the largest of the two operands by 1000, to improve precision of the
-                // result.
-                if duration_ms > freq {
-                    match (duration_ms / 1000).checked_mul(freq) {
-                        Some(y) => y,
-                        None => return Err(OtherError::TimerDriverDurationOutOfRange.into()),
-                    }
-                } else {
-                    match (freq / 1000).checked_mul(duration_ms) {
-                        Some(y) => y,
-                        None => return Err(OtherError::TimerDriverDurationOutOfRange.into()),
-                    }
-                }
-            }
-        };
-        let alarm_instant = now.num_ticks() as usize + ticks;
+        let ticks = duration_to_ticks(duration.into(), self.clock_frequency.hz())?;
+        let alarm_instant = (now.num_ticks() as usize).wrapping_add(ticks);
 
-        let alarm_id = syscalls::command(DRIVER_NUMBER, command_nr::SET_ALARM, alarm_instant, 0)?;
+        let alarm_id =
+            syscalls::command_u32(DRIVER_NUMBER, command_nr::SET_ALARM, alarm_instant, 0)? as usize;
+
+        Ok(Alarm { alarm_id })
+    }
+
+    /// Sets an alarm for the given clock value, e.g. `previous_alarm + period`. Unlike
+    /// [set_alarm](Timer::set_alarm), this does not depend on the time passed since the clock
+    /// value has been read, so periodic alarms do not drift.
+    pub fn set_alarm_at(&mut self, clock_value: ClockValue) -> TockResult<Alarm> {
+        let alarm_id = syscalls::command_u32(
+            DRIVER_NUMBER,
+            command_nr::SET_ALARM,
+            clock_value.num_ticks as usize,
+            0,
+        )? as usize;
 
         Ok(Alarm { alarm_id })
     }
@@ -169,6 +169,50 @@ impl ClockValue {
     pub fn ms_f64(self) -> f64 {
         1000.0 * (self.num_ticks as f64) / (self.clock_frequency.hz() as f64)
     }
+
+    /// The clock value in microseconds. Unlike [ms](ClockValue::ms), this does not lose
+    /// precision for large clock values.
+    pub fn us(self) -> i64 {
+        let ticks = i64::from(self.num_ticks as u32);
+        let hz = self.clock_frequency.hz() as i64;
+        ticks / hz * 1_000_000 + ticks % hz * 1_000_000 / hz
+    }
+
+    /// The clock value as duration since the counter has last wrapped around, with nanosecond
+    /// precision.
+    pub fn as_duration(self) -> core::time::Duration {
+        ticks_to_duration(u64::from(self.num_ticks as u32), self.clock_frequency.hz())
+    }
+}
+
+/// Advances the clock value by the duration. Like the clock, the result wraps around.
+impl Add<Duration<isize>> for ClockValue {
+    type Output = ClockValue;
+
+    fn add(self, duration: Duration<isize>) -> ClockValue {
+        let ticks = ms_to_ticks(duration.ms(), self.clock_frequency.hz());
+        ClockValue {
+            num_ticks: self.num_ticks.wrapping_add(ticks as isize),
+            clock_frequency: self.clock_frequency,
+        }
+    }
+}
+
+/// Advances the clock value by the duration. Like the clock, the result wraps around.
+impl Add<core::time::Duration> for ClockValue {
+    type Output = ClockValue;
+
+    fn add(self, duration: core::time::Duration) -> ClockValue {
+        let hz = self.clock_frequency.hz() as u64;
+        let ticks = duration
+            .as_secs()
+            .wrapping_mul(hz)
+            .wrapping_add(u64::from(duration.subsec_nanos()) * hz / 1_000_000_000);
+        ClockValue {
+            num_ticks: self.num_ticks.wrapping_add(ticks as isize),
+            clock_frequency: self.clock_frequency,
+        }
+    }
 }
 
 pub struct Alarm {
@@ -201,6 +245,28 @@ where
     }
 }
 
+macro_rules! impl_into_core_duration {
+    ($($unsigned:ty),* ; $($signed:ty),*) => {
+        $(
+            impl From<Duration<$unsigned>> for core::time::Duration {
+                fn from(duration: Duration<$unsigned>) -> core::time::Duration {
+                    core::time::Duration::from_millis(duration.ms as u64)
+                }
+            }
+        )*
+        $(
+            /// Negative durations are converted to zero.
+            impl From<Duration<$signed>> for core::time::Duration {
+                fn from(duration: Duration<$signed>) -> core::time::Duration {
+                    core::time::Duration::from_millis(duration.ms.max(0) as u64)
+                }
+            }
+        )*
+    };
+}
+
+impl_into_core_duration!(u8, u16, u32, u64, usize; i8, i16, i32, i64, isize);
+
 impl<T> Sub for Duration<T>
 where
     T: Sub<Output = T>,
@@ -279,7 +345,162 @@ where
     }
 }
 
-#[derive(Copy, Clone, Default, PartialEq, Eq)]
+/// A point in time of a monotonic clock which does not wrap around. The ticks of the hardware
+/// counter, which is 24 or 32 bits wide, are extended to 64 bits in software whenever the timer
+/// driver reads the counter. To detect every wrap-around, the counter has to be read at least once
+/// per wrap-around period. While a [ParallelSleepDriver] is activated, its alarm does so every
+/// quarter of the period. The clock starts at the first value read from the counter.
+///
+/// ```no_run
+/// # use libtock::result::TockResult;
+/// # use libtock::timer::Instant;
+/// # fn doc() -> TockResult<()> {
+/// let start = Instant::now()?;
+/// // Do some work
+/// let elapsed = start.elapsed()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Copy, Clone, Debug)]
+pub struct Instant {
+    ticks: u64,
+    clock_frequency: ClockFrequency,
+}
+
+impl Instant {
+    pub fn now() -> TockResult<Instant> {
+        get_current_ticks()?;
+        let hz = get_nonzero_clock_frequency()?;
+        Ok(Instant {
+            ticks: with_extended_clock(|clock| clock.ticks()),
+            clock_frequency: ClockFrequency { hz },
+        })
+    }
+
+    pub fn ticks(self) -> u64 {
+        self.ticks
+    }
+
+    pub fn clock_frequency(self) -> ClockFrequency {
+        self.clock_frequency
+    }
+
+    /// The time since the clock started.
+    pub fn as_duration(self) -> core::time::Duration {
+        ticks_to_duration(self.ticks, self.clock_frequency.hz())
+    }
+
+    /// The time passed since `earlier`, or zero if `earlier` is later than `self`.
+    pub fn duration_since(self, earlier: Instant) -> core::time::Duration {
+        ticks_to_duration(
+            self.ticks.saturating_sub(earlier.ticks),
+            self.clock_frequency.hz(),
+        )
+    }
+
+    pub fn elapsed(self) -> TockResult<core::time::Duration> {
+        Ok(Instant::now()?.duration_since(self))
+    }
+}
+
+impl PartialEq for Instant {
+    fn eq(&self, other: &Instant) -> bool {
+        self.ticks == other.ticks
+    }
+}
+
+impl Eq for Instant {}
+
+impl PartialOrd for Instant {
+    fn partial_cmp(&self, other: &Instant) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Instant {
+    fn cmp(&self, other: &Instant) -> core::cmp::Ordering {
+        self.ticks.cmp(&other.ticks)
+    }
+}
+
+impl Add<core::time::Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: core::time::Duration) -> Instant {
+        let hz = self.clock_frequency.hz() as u64;
+        let ticks = duration
+            .as_secs()
+            .saturating_mul(hz)
+            .saturating_add(u64::from(duration.subsec_nanos()) * hz / 1_000_000_000);
+        Instant {
+            ticks: self.ticks.saturating_add(ticks),
+            clock_frequency: self.clock_frequency,
+        }
+    }
+}
+
+impl Sub for Instant {
+    type Output = core::time::Duration;
+
+    fn sub(self, earlier: Instant) -> core::time::Duration {
+        self.duration_since(earlier)
+    }
+}
+
+fn ticks_to_duration(ticks: u64, hz: usize) -> core::time::Duration {
+    let hz = hz as u64;
+    let nanos = (ticks % hz) * 1_000_000_000 / hz;
+    core::time::Duration::new(ticks / hz, nanos as u32)
+}
+
+/// Extends the values read from the hardware counter to 64 bits.
+#[derive(Copy, Clone, Default)]
+struct ExtendedClock {
+    last_value: u32,
+    wrapped_ticks: u64,
+    /// The width of the counter in bits, or 0 until it has been read from the kernel.
+    counter_width: u32,
+}
+
+impl ExtendedClock {
+    fn update(&mut self, value: u32) {
+        if value < self.last_value {
+            self.wrapped_ticks += 1 << self.counter_width;
+        }
+        self.last_value = value;
+    }
+
+    fn ticks(&self) -> u64 {
+        self.wrapped_ticks + u64::from(self.last_value)
+    }
+}
+
+#[cfg(test)]
+fn with_extended_clock<R, F: FnOnce(&mut ExtendedClock) -> R>(f: F) -> R {
+    thread_local!(static EXTENDED_CLOCK: Cell<ExtendedClock> = Cell::new(ExtendedClock::default()));
+    EXTENDED_CLOCK.with(|extended_clock| {
+        let mut clock = extended_clock.get();
+        let result = f(&mut clock);
+        extended_clock.set(clock);
+        result
+    })
+}
+
+#[cfg(not(test))]
+fn with_extended_clock<R, F: FnOnce(&mut ExtendedClock) -> R>(f: F) -> R {
+    static mut EXTENDED_CLOCK: ExtendedClock = ExtendedClock {
+        last_value: 0,
+        wrapped_ticks: 0,
+        counter_width: 0,
+    };
+    // Apps are single-threaded and the closures passed in here do not call back into this function.
+    let mut clock = unsafe { EXTENDED_CLOCK };
+    let result = f(&mut clock);
+    unsafe { EXTENDED_CLOCK = clock };
+    result
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 pub(crate) struct ActiveTimer {
     instant: u32,
     set_at: u32,
@@ -304,8 +525,7 @@ impl DriverContext {
     /// Create a driver timer from a context.
     pub fn create_timer_driver(&mut self) -> TimerDriver {
         TimerDriver {
-            callback: Callback,
-            context: self,
+            callback: Callback { context: self },
         }
     }
 
@@ -330,16 +550,20 @@ impl DriverContext {
 /// # }
 /// ```
 pub struct TimerDriver<'a> {
-    callback: Callback,
-    context: &'a DriverContext,
+    callback: Callback<'a>,
 }
 
-struct Callback;
+struct Callback<'a> {
+    context: &'a DriverContext,
+}
 
 struct ParallelTimerConsumer;
 
-impl<'a> Consumer<Callback> for ParallelTimerConsumer {
-    fn consume(_: &mut Callback, _: usize, _: usize, _: usize) {}
+impl<'a> Consumer<Callback<'a>> for ParallelTimerConsumer {
+    fn consume(callback: &mut Callback<'a>, _: usize, _: usize, _: usize) {
+        // The alarm has fired, so it is armed again. Errors cannot be reported from here.
+        let _ = arm_alarm(callback.context);
+    }
 }
 
 /// Activated time driver. Updates current time in the context and manages
@@ -365,6 +589,7 @@ impl<'a> TimerDriver<'a> {
     /// Activate the timer driver, will return a ParallelSleepDriver which
     /// can used to sleep.
     pub fn activate(&'a mut self) -> TockResult<ParallelSleepDriver<'a>> {
+        let context = self.callback.context;
         let subscription = syscalls::subscribe::<ParallelTimerConsumer, _>(
             DRIVER_NUMBER,
             subscribe_nr::SUBSCRIBE_CALLBACK,
@@ -372,40 +597,163 @@ impl<'a> TimerDriver<'a> {
         )?;
         let driver = ParallelSleepDriver {
             _callback_subscription: subscription,
-            context: &self.context,
+            context,
         };
+        arm_alarm(context)?;
         Ok(driver)
     }
 }
 
 impl<'a> ParallelSleepDriver<'a> {
-    /// Sleep for the given duration
-    pub async fn sleep(&self, duration: Duration<usize>) -> TockResult<()> {
+    /// Sleep for the given duration, which is either a [Duration] or a `core::time::Duration`.
+    pub async fn sleep<D: Into<core::time::Duration>>(&self, duration: D) -> TockResult<()> {
         let now = get_current_ticks()?;
         let freq = get_clock_frequency()?;
-        let alarm_instant = Self::compute_alarm_instant(duration.ms, now, freq)?;
-        let this_alarm = ActiveTimer {
+        let alarm_instant = Self::compute_alarm_instant(duration.into(), now, freq)?;
+        self.wait_for_alarm(ActiveTimer {
             instant: alarm_instant as u32,
             set_at: now as u32,
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Returns the current clock value as timestamp, to be used with
+    /// [sleep_until](ParallelSleepDriver::sleep_until).
+    pub fn now(&self) -> TockResult<Timestamp<isize>> {
+        let now = get_current_ticks()?;
+        let freq = get_nonzero_clock_frequency()?;
+        Ok(Timestamp::from_ms(ticks_to_ms(now as u32, freq)))
+    }
+
+    /// Sleep until the clock reaches the given timestamp. Unlike [sleep](ParallelSleepDriver::sleep),
+    /// the wake-up time does not depend on when the sleep is started, so loops running at a fixed
+    /// rate do not drift by their processing time. Timestamps up to half a wrap-around of the
+    /// clock in the past have already elapsed and return immediately.
+    /// ```no_run
+    /// # use libtock::result::TockResult;
+    /// # use libtock::timer::Duration;
+    /// # async fn doc() -> TockResult<()> {
+    /// # let mut drivers = libtock::retrieve_drivers()?;
+    /// # let mut timer_driver = drivers.timer.create_timer_driver();
+    /// # let timer_driver = timer_driver.activate()?;
+    /// let mut next_sample = timer_driver.now()?;
+    /// loop {
+    ///     next_sample += Duration::from_ms(100);
+    ///     timer_driver.sleep_until(next_sample).await?;
+    ///     // Take a sample
+    /// }
+    /// # }
+    /// ```
+    pub async fn sleep_until(&self, timestamp: Timestamp<isize>) -> TockResult<()> {
+        let now = get_current_ticks()? as u32;
+        let freq = get_nonzero_clock_frequency()?;
+        let instant = ms_to_ticks(timestamp.ms(), freq) as u32;
+        if has_elapsed(instant, now) {
+            return Ok(());
+        }
+        self.wait_for_alarm(ActiveTimer {
+            instant,
+            set_at: now,
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Creates an [Interval] ticking every `period`, starting one period from now.
+    /// ```no_run
+    /// # use futures::stream::StreamExt;
+    /// # use libtock::result::TockResult;
+    /// # use libtock::timer::Duration;
+    /// # use libtock::timer::MissedTickBehavior;
+    /// # async fn doc() -> TockResult<()> {
+    /// # let mut drivers = libtock::retrieve_drivers()?;
+    /// # let mut timer_driver = drivers.timer.create_timer_driver();
+    /// # let timer_driver = timer_driver.activate()?;
+    /// let mut interval = timer_driver
+    ///     .interval(Duration::from_ms(50))?
+    ///     .with_missed_tick_behavior(MissedTickBehavior::Skip);
+    /// while let Some(tick) = interval.next().await {
+    ///     tick?;
+    ///     // Take a sample
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn interval<'b, D: Into<core::time::Duration>>(
+        &'b self,
+        period: D,
+    ) -> TockResult<Interval<'a, 'b>> {
+        let period = period.into();
+        let freq = get_nonzero_clock_frequency()?;
+        let period_ticks = duration_to_ticks(period, freq)?;
+        if period_ticks == 0 || period_ticks >= 1 << 31 {
+            return Err(OtherError::TimerDriverDurationOutOfRange.into());
+        }
+        let period_remainder = u64::from(period.subsec_nanos()) * freq as u64 % 1_000_000_000;
+        let mut interval = Interval {
+            driver: self,
+            freq,
+            period_ticks: period_ticks as u32,
+            period_remainder: period_remainder as u32,
+            remainder: 0,
+            next_tick: get_current_ticks()? as u32,
+            missed_tick_behavior: MissedTickBehavior::Burst,
+            sleep: None,
         };
+        interval.advance();
+        Ok(interval)
+    }
 
-        let suspended_timer: Cell<Option<ActiveTimer>> = Cell::new(None);
+    async fn wait_for_alarm(&self, this_alarm: ActiveTimer) {
+        let sleep = Sleep {
+            driver: self,
+            alarm: this_alarm,
+        };
 
-        futures::wait_until(|| {
-            self.activate_current_timer(this_alarm, &suspended_timer)
-                .unwrap_or(false)
+        futures::wait_until_on_upcall(DRIVER_NUMBER, subscribe_nr::SUBSCRIBE_CALLBACK, || {
+            self.activate_current_timer(this_alarm).unwrap_or(false)
         })
         .await;
+        mem::forget(sleep);
+    }
 
-        Ok(())
+    /// Run the future until it completes, but at most for the given duration. If the duration
+    /// elapses first, the future is dropped and `OtherError::TimedOut` is returned. Otherwise,
+    /// the alarm is stopped.
+    /// ```no_run
+    /// # use libtock::result::TockResult;
+    /// # use libtock::timer::Duration;
+    /// # async fn doc() -> TockResult<()> {
+    /// # let mut drivers = libtock::retrieve_drivers()?;
+    /// # let mut timer_driver = drivers.timer.create_timer_driver();
+    /// # let timer_driver = timer_driver.activate()?;
+    /// # let some_future = async {};
+    /// let output = timer_driver
+    ///     .timeout(some_future, Duration::from_ms(2000))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn timeout<F: Future, D: Into<core::time::Duration>>(
+        &self,
+        future: F,
+        duration: D,
+    ) -> TockResult<F::Output> {
+        match futures::select(future, self.sleep(duration)).await {
+            Either::Left(output) => Ok(output),
+            Either::Right(sleep_result) => {
+                sleep_result?;
+                Err(OtherError::TimedOut.into())
+            }
+        }
     }
 
     fn activate_timer(&self, timer: ActiveTimer) -> TockResult<()> {
-        set_alarm_at(timer.instant as usize)?;
+        self.context.active_timer.set(Some(timer));
+        arm_alarm(self.context)?;
         let now = get_current_ticks()?;
-        if !is_over(timer, now as u32) {
-            self.context.active_timer.set(Some(timer));
-        } else {
+        if is_over(timer, now as u32) {
             self.wakeup_soon()?;
         }
         Ok(())
@@ -433,69 +781,246 @@ impl<'a> ParallelSleepDriver<'a> {
     }
 
     fn compute_alarm_instant(
-        duration_ms: usize,
+        duration: core::time::Duration,
         num_ticks: usize,
         freq: usize,
     ) -> TockResult<usize> {
-        let ticks = match duration_ms.checked_mul(freq) {
-            Some(x) => x / 1000,
-            None => {
-                // Divide the largest of the two operands by 1000, to improve precision of the
-                // result.
-                if duration_ms > freq {
-                    match (duration_ms / 1000).checked_mul(freq) {
-                        Some(y) => y,
-                        None => {
-                            return Err(TockError::Other(OtherError::TimerDriverDurationOutOfRange))
-                        }
-                    }
-                } else {
-                    match (freq / 1000).checked_mul(duration_ms) {
-                        Some(y) => y,
-                        None => {
-                            return Err(TockError::Other(OtherError::TimerDriverDurationOutOfRange))
-                        }
-                    }
-                }
-            }
-        };
-        let alarm_instant = num_ticks + ticks;
-        Ok(alarm_instant)
+        let ticks = duration_to_ticks(duration, freq)?;
+        Ok(num_ticks.wrapping_add(ticks))
     }
 
-    fn activate_current_timer(
-        &self,
-        this_alarm: ActiveTimer,
-        suspended_timer: &Cell<Option<ActiveTimer>>,
-    ) -> TockResult<bool> {
+    /// Activates the alarm if it is earlier than the active one and returns whether it is over.
+    /// Only the earliest pending alarm is set in the kernel. Whenever it is cleared, all pending
+    /// sleeps are polled again, so the next one can take over.
+    fn activate_current_timer(&self, this_alarm: ActiveTimer) -> TockResult<bool> {
         let now = get_current_ticks()?;
 
-        if let Some(active) = self.context.active_timer.get() {
-            if left_is_later(active, this_alarm) {
-                suspended_timer.set(Some(active));
-                self.activate_timer(this_alarm)?;
+        if is_over(this_alarm, now as u32) {
+            if self.context.active_timer.get() == Some(this_alarm) {
+                self.deactivate_timer();
             }
-        } else {
-            self.activate_timer(this_alarm)?;
+            return Ok(true);
         }
-        if is_over(this_alarm, now as u32) {
-            if let Some(paused) = suspended_timer.get() {
-                self.activate_timer(paused)?;
-            } else {
-                self.context.active_timer.set(None);
+        match self.context.active_timer.get() {
+            Some(active) if !left_is_later(active, this_alarm) => {}
+            _ => self.activate_timer(this_alarm)?,
+        }
+        Ok(false)
+    }
+
+    fn deactivate_timer(&self) {
+        self.context.active_timer.set(None);
+        callback::wake_upcall_wakers_of(DRIVER_NUMBER, subscribe_nr::SUBSCRIBE_CALLBACK);
+    }
+}
+
+/// Blocks for the given number of milliseconds. Errors of the timer driver cannot be reported, so
+/// the delay ends early if the alarm cannot be set.
+#[cfg(feature = "embedded-hal")]
+impl<'a, T: Into<u32>> embedded_hal::blocking::delay::DelayMs<T> for ParallelSleepDriver<'a> {
+    fn delay_ms(&mut self, ms: T) {
+        let duration = core::time::Duration::from_millis(ms.into().into());
+        let _ = unsafe { executor::block_on(self.sleep(duration)) };
+    }
+}
+
+/// Blocks for the given number of microseconds with the precision of a clock tick. Errors of the
+/// timer driver cannot be reported, so the delay ends early if the alarm cannot be set.
+#[cfg(feature = "embedded-hal")]
+impl<'a, T: Into<u32>> embedded_hal::blocking::delay::DelayUs<T> for ParallelSleepDriver<'a> {
+    fn delay_us(&mut self, us: T) {
+        let duration = core::time::Duration::from_micros(us.into().into());
+        let _ = unsafe { executor::block_on(self.sleep(duration)) };
+    }
+}
+
+/// A sleep in progress. If it is dropped before it has finished while its alarm is active, the
+/// alarm is stopped and the next pending sleep is activated.
+struct Sleep<'a, 'b> {
+    driver: &'b ParallelSleepDriver<'a>,
+    alarm: ActiveTimer,
+}
+
+impl<'a, 'b> Drop for Sleep<'a, 'b> {
+    fn drop(&mut self) {
+        if self.driver.context.active_timer.get() != Some(self.alarm) {
+            return;
+        }
+        self.driver.deactivate_timer();
+        // Errors cannot be reported from here. At worst, a spurious upcall is delivered.
+        let _ = arm_alarm(self.driver.context);
+    }
+}
+
+/// What an [Interval] does if ticks have been missed because it has not been polled in time.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MissedTickBehavior {
+    /// Deliver the missed ticks immediately one after the other, then continue at the original
+    /// rate.
+    Burst,
+    /// Deliver one tick immediately and restart the interval from there.
+    Delay,
+    /// Deliver one tick immediately and drop the other missed ticks, s.t. the following ticks
+    /// stay in phase with the original schedule.
+    Skip,
+}
+
+/// Stream of ticks at a fixed rate, created by [ParallelSleepDriver::interval]. The ticks are
+/// scheduled at absolute clock values, so the interval does not drift by the time spent between
+/// ticks. Each tick yields the timestamp it has been scheduled for.
+pub struct Interval<'a, 'b> {
+    driver: &'b ParallelSleepDriver<'a>,
+    freq: usize,
+    period_ticks: u32,
+    /// Fractional part of the period in billionths of a tick, accumulated in `remainder`.
+    period_remainder: u32,
+    remainder: u32,
+    next_tick: u32,
+    missed_tick_behavior: MissedTickBehavior,
+    sleep: Option<Sleep<'a, 'b>>,
+}
+
+impl<'a, 'b> Interval<'a, 'b> {
+    pub fn with_missed_tick_behavior(self, missed_tick_behavior: MissedTickBehavior) -> Self {
+        Interval {
+            missed_tick_behavior,
+            ..self
+        }
+    }
+
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    /// Waits for the next tick.
+    pub async fn tick(&mut self) -> TockResult<Timestamp<isize>> {
+        ::futures::future::poll_fn(|cx| self.poll_tick(cx)).await
+    }
+
+    fn poll_tick(&mut self, cx: &mut Context) -> Poll<TockResult<Timestamp<isize>>> {
+        match self.try_poll_tick(cx) {
+            Ok(Some(tick)) => Poll::Ready(Ok(tick)),
+            Ok(None) => Poll::Pending,
+            Err(error) => Poll::Ready(Err(error)),
+        }
+    }
+
+    fn try_poll_tick(&mut self, cx: &mut Context) -> TockResult<Option<Timestamp<isize>>> {
+        let now = get_current_ticks()? as u32;
+        if self.sleep.is_none() && !has_elapsed(self.next_tick, now) {
+            self.sleep = Some(Sleep {
+                driver: self.driver,
+                alarm: ActiveTimer {
+                    instant: self.next_tick,
+                    set_at: now,
+                },
+            });
+        }
+        if let Some(sleep) = &self.sleep {
+            if !self.driver.activate_current_timer(sleep.alarm)? {
+                callback::register_upcall_waker(
+                    DRIVER_NUMBER,
+                    subscribe_nr::SUBSCRIBE_CALLBACK,
+                    cx.waker(),
+                );
+                return Ok(None);
             }
-            Ok(true)
-        } else {
-            Ok(false)
+            mem::forget(self.sleep.take());
+        }
+
+        let tick = self.next_tick;
+        let now = get_current_ticks()? as u32;
+        let missed = now.wrapping_sub(tick) >= self.period_ticks;
+        match self.missed_tick_behavior {
+            MissedTickBehavior::Delay if missed => {
+                self.next_tick = now;
+                self.remainder = 0;
+                self.advance();
+            }
+            MissedTickBehavior::Skip if missed => {
+                while has_elapsed(self.next_tick, now) {
+                    self.advance();
+                }
+            }
+            _ => self.advance(),
         }
+        Ok(Some(Timestamp::from_ms(ticks_to_ms(tick, self.freq))))
+    }
+
+    fn advance(&mut self) {
+        self.remainder += self.period_remainder;
+        self.next_tick = self
+            .next_tick
+            .wrapping_add(self.period_ticks + self.remainder / 1_000_000_000);
+        self.remainder %= 1_000_000_000;
+    }
+}
+
+impl<'a, 'b> ::futures::Stream for Interval<'a, 'b> {
+    type Item = TockResult<Timestamp<isize>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_tick(cx).map(Some)
     }
 }
 
+/// Reads the hardware counter and extends its value for [Instant].
 fn get_current_ticks() -> TockResult<usize> {
-    syscalls::command(DRIVER_NUMBER, command_nr::GET_CLOCK_VALUE, 0, 0).map_err(|err| err.into())
+    get_counter_width()?;
+    let ticks = syscalls::command_u32(DRIVER_NUMBER, command_nr::GET_CLOCK_VALUE, 0, 0)?;
+    with_extended_clock(|clock| clock.update(ticks));
+    Ok(ticks as usize)
+}
+
+/// The width of the hardware counter in bits. Kernels which do not report it scale the counter to
+/// 32 bits.
+fn get_counter_width() -> TockResult<u32> {
+    let counter_width = with_extended_clock(|clock| clock.counter_width);
+    if counter_width != 0 {
+        return Ok(counter_width);
+    }
+    let counter_width =
+        match syscalls::command_u32(DRIVER_NUMBER, command_nr::GET_COUNTER_WIDTH, 0, 0) {
+            Ok(width @ 16..=32) => width,
+            Ok(_) => return Err(OtherError::TimerDriverErroneousCounterWidth.into()),
+            Err(error) if error.error_code == ErrorCode::NoSupport => 32,
+            Err(error) => return Err(error.into()),
+        };
+    with_extended_clock(|clock| clock.counter_width = counter_width);
+    Ok(counter_width)
+}
+
+/// The number of ticks after which the hardware counter wraps around.
+fn get_wrap_around_ticks() -> TockResult<u64> {
+    Ok(1 << get_counter_width()?)
+}
+
+/// The time after which the hardware counter wraps around.
+pub(crate) fn wrap_around_period() -> TockResult<core::time::Duration> {
+    let freq = get_nonzero_clock_frequency()?;
+    Ok(ticks_to_duration(get_wrap_around_ticks()?, freq))
+}
+
+/// Sets the alarm to the active timer, but at most a quarter of the wrap-around period ahead.
+/// The upcalls of the alarm read the counter, so [Instant] detects every wrap-around even if the
+/// app does not sleep.
+fn arm_alarm(context: &DriverContext) -> TockResult<()> {
+    let now = get_current_ticks()? as u32;
+    let wrap_around_ticks = get_wrap_around_ticks()?;
+    let mask = (wrap_around_ticks - 1) as u32;
+    let max_ticks = (wrap_around_ticks / 4) as u32;
+    let instant = match context.active_timer.get() {
+        Some(timer)
+            if !is_over(timer, now) && timer.instant.wrapping_sub(now) & mask <= max_ticks =>
+        {
+            timer.instant
+        }
+        _ => now.wrapping_add(max_ticks) & mask,
+    };
+    set_alarm_at(instant as usize)
 }
 fn set_alarm_at(instant: usize) -> TockResult<()> {
-    syscalls::command(DRIVER_NUMBER, command_nr::SET_ALARM, instant, 0)
+    syscalls::command_u32(DRIVER_NUMBER, command_nr::SET_ALARM, instant, 0)
         .map(|_| ())
         .map_err(|err| err.into())
 }
@@ -503,18 +1028,66 @@ fn set_alarm_at(instant: usize) -> TockResult<()> {
 fn stop_alarm_at(instant: usize) -> TockResult<()> {
     match syscalls::command(DRIVER_NUMBER, command_nr::STOP_ALARM, instant, 0) {
         Ok(_) => Ok(()),
-        Err(error) => match error.return_code {
-            EALREADY => Ok(()),
+        Err(error) => match error.error_code {
+            ErrorCode::Already => Ok(()),
             _ => Err(TockError::Command(error)),
         },
     }
 }
 
 fn get_clock_frequency() -> TockResult<usize> {
-    syscalls::command(DRIVER_NUMBER, command_nr::GET_CLOCK_FREQUENCY, 0, 0)
+    syscalls::command_u32(DRIVER_NUMBER, command_nr::GET_CLOCK_FREQUENCY, 0, 0)
+        .map(|hz| hz as usize)
         .map_err(|err| err.into())
 }
 
+fn get_nonzero_clock_frequency() -> TockResult<usize> {
+    match get_clock_frequency()? {
+        0 => Err(OtherError::TimerDriverErroneousClockFrequency.into()),
+        freq => Ok(freq),
+    }
+}
+
+/// Converts the duration to clock ticks, rounding down. Fails if the result does not fit into a
+/// `usize`.
+fn duration_to_ticks(duration: core::time::Duration, freq: usize) -> TockResult<usize> {
+    let freq = freq as u64;
+    let ticks = duration.as_secs().checked_mul(freq).and_then(|ticks| {
+        ticks.checked_add(u64::from(duration.subsec_nanos()) * freq / 1_000_000_000)
+    });
+    match ticks {
+        Some(ticks) if ticks <= usize::MAX as u64 => Ok(ticks as usize),
+        _ => Err(OtherError::TimerDriverDurationOutOfRange.into()),
+    }
+}
+
+/// Converts milliseconds to clock ticks, wrapping around like the clock.
+fn ms_to_ticks(ms: isize, freq: usize) -> i64 {
+    let ms = ms as i64;
+    let freq = freq as i64;
+    (ms / 1000)
+        .wrapping_mul(freq)
+        .wrapping_add(ms % 1000 * freq / 1000)
+}
+
+/// Converts clock ticks to milliseconds like [ClockValue::ms], but without losing precision for
+/// large clock values.
+fn ticks_to_ms(ticks: u32, freq: usize) -> isize {
+    let ticks = i64::from(ticks);
+    let freq = freq as i64;
+    (ticks / freq * 1000 + ticks % freq * 1000 / freq) as isize
+}
+
+/// Whether the clock value has been reached, assuming it lies at most half a wrap-around of the
+/// clock in the past.
+fn has_elapsed(instant: u32, now: u32) -> bool {
+    let half_wrap_before = ActiveTimer {
+        instant,
+        set_at: instant.wrapping_sub(1 << 31),
+    };
+    is_over(half_wrap_before, now)
+}
+
 fn is_over(timer: ActiveTimer, now: u32) -> bool {
     now.wrapping_sub(timer.set_at) >= timer.instant.wrapping_sub(timer.set_at)
 }
@@ -535,36 +1108,778 @@ fn left_is_later(alarm_1: ActiveTimer, alarm_2: ActiveTimer) -> bool {
     false
 }
 
+#[cfg(test)]
+pub(crate) mod fake {
+    use super::*;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeDriver;
+    use crate::syscalls::CommandReturn;
+    use std::rc::Rc;
+
+    pub(crate) const DRIVER_NUMBER: usize = super::DRIVER_NUMBER;
+
+    /// A 32 bit timer running at 1 kHz by default. Time only passes while the app waits for an
+    /// upcall. A counter width of 0 simulates kernels which do not report the width.
+    pub(crate) struct FakeTimer {
+        pub(crate) frequency: u32,
+        pub(crate) counter_width: u32,
+        pub(crate) now: Rc<Cell<u32>>,
+        pub(crate) alarm: Rc<Cell<Option<u32>>>,
+    }
+
+    impl Default for FakeTimer {
+        fn default() -> FakeTimer {
+            FakeTimer {
+                frequency: 1000,
+                counter_width: 32,
+                now: Rc::default(),
+                alarm: Rc::default(),
+            }
+        }
+    }
+
+    impl FakeDriver for FakeTimer {
+        fn command(
+            &mut self,
+            _: &mut DriverHandle,
+            command_number: usize,
+            arg1: usize,
+            _: usize,
+        ) -> CommandReturn {
+            match command_number {
+                command_nr::IS_DRIVER_AVAILABLE => CommandReturn::Success,
+                command_nr::GET_CLOCK_FREQUENCY => CommandReturn::SuccessU32(self.frequency),
+                command_nr::GET_CLOCK_VALUE => CommandReturn::SuccessU32(self.now.get()),
+                command_nr::GET_COUNTER_WIDTH if self.counter_width != 0 => {
+                    CommandReturn::SuccessU32(self.counter_width)
+                }
+                command_nr::SET_ALARM => {
+                    self.alarm.set(Some(arg1 as u32));
+                    CommandReturn::SuccessU32(arg1 as u32)
+                }
+                command_nr::STOP_ALARM => match self.alarm.take() {
+                    Some(_) => CommandReturn::Success,
+                    None => CommandReturn::Failure(ErrorCode::Already),
+                },
+                _ => CommandReturn::Failure(ErrorCode::NoSupport),
+            }
+        }
+
+        fn idle(&mut self, kernel: &mut DriverHandle) {
+            if let Some(alarm) = self.alarm.take() {
+                self.now.set(alarm);
+                kernel.schedule_upcall(subscribe_nr::SUBSCRIBE_CALLBACK, (alarm as usize, 0, 0));
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use super::fake::FakeTimer;
     use super::*;
+    use crate::callback::Identity0Consumer;
+    use crate::executor;
+    use crate::executor::Executor;
+    use crate::executor::Task;
+    use crate::sync::Signal;
+    use crate::syscalls::fake_kernel::DriverHandle;
+    use crate::syscalls::fake_kernel::FakeDriver;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use crate::syscalls::CommandReturn;
+    use ::futures::stream::StreamExt;
+    use core::cell::RefCell;
+
     #[test]
-    pub fn duration_bigger_than_frequency() {
-        let x = ParallelSleepDriver::compute_alarm_instant(10000, 0, 1000)
-            .ok()
+    pub fn timeout_expires_if_the_future_does_not_complete() {
+        let timer = FakeTimer::default();
+        let now = timer.now.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        let result = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            unsafe {
+                executor::block_on(
+                    timer_driver.timeout(::futures::future::pending::<()>(), Duration::from_ms(20)),
+                )
+            }
+        });
+
+        match result {
+            Err(TockError::Other(OtherError::TimedOut)) => {}
+            other => panic!("Expected a timeout, got {:?}", other),
+        }
+        assert_eq!(now.get(), 20);
+    }
+
+    #[test]
+    pub fn timeout_cleans_up_the_alarm_on_either_outcome() {
+        let timer = FakeTimer::default();
+        let now = timer.now.clone();
+        let alarm = timer.alarm.clone();
+        let alarm_in_app = timer.alarm.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        let (timed_out, alarm_after_timeout, completed) = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            unsafe {
+                executor::block_on(async {
+                    let timed_out = timer_driver
+                        .timeout(
+                            timer_driver.sleep(Duration::from_ms(20)),
+                            Duration::from_ms(10),
+                        )
+                        .await;
+                    let alarm_after_timeout = alarm_in_app.get();
+                    let completed = timer_driver
+                        .timeout(
+                            timer_driver.sleep(Duration::from_ms(5)),
+                            Duration::from_ms(20),
+                        )
+                        .await;
+                    (timed_out, alarm_after_timeout, completed)
+                })
+            }
+        });
+
+        match timed_out {
+            Err(TockError::Other(OtherError::TimedOut)) => {}
+            other => panic!("Expected a timeout, got {:?}", other),
+        }
+        assert_eq!(alarm_after_timeout, keep_alive_alarm(10));
+        match completed {
+            Ok(Ok(())) => {}
+            other => panic!("Expected the sleep to complete, got {:?}", other),
+        }
+        assert_eq!(alarm.get(), keep_alive_alarm(15));
+        assert_eq!(now.get(), 15);
+    }
+
+    #[test]
+    pub fn select_returns_the_first_completed_future() {
+        let timer = FakeTimer::default();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        let winner = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            unsafe {
+                executor::block_on(futures::select(
+                    timer_driver.sleep(Duration::from_ms(30)),
+                    timer_driver.sleep(Duration::from_ms(10)),
+                ))
+            }
+        });
+
+        match winner {
+            Either::Right(Ok(())) => {}
+            _ => panic!("Expected the shorter sleep to win"),
+        }
+    }
+
+    #[test]
+    pub fn sleep_until_does_not_drift_by_the_processing_time() {
+        let timer = FakeTimer::default();
+        let now = timer.now.clone();
+        let now_in_app = timer.now.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        let wake_ups = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            unsafe {
+                executor::block_on(async {
+                    let mut wake_ups = Vec::new();
+                    let mut deadline = timer_driver.now().unwrap();
+                    for _ in 0..3 {
+                        deadline += Duration::from_ms(10);
+                        timer_driver.sleep_until(deadline).await.unwrap();
+                        wake_ups.push(now_in_app.get());
+                        // Processing takes 3 ms.
+                        now_in_app.set(now_in_app.get() + 3);
+                    }
+                    wake_ups
+                })
+            }
+        });
+
+        assert_eq!(wake_ups, vec![10, 20, 30]);
+        assert_eq!(now.get(), 33);
+    }
+
+    #[test]
+    pub fn sleep_until_returns_immediately_for_past_timestamps() {
+        let timer = FakeTimer::default();
+        timer.now.set(100);
+        let now = timer.now.clone();
+        let alarm = timer.alarm.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            unsafe {
+                executor::block_on(async {
+                    timer_driver.sleep_until(Timestamp::from_ms(90)).await?;
+                    timer_driver.sleep_until(Timestamp::from_ms(100)).await
+                })
+            }
+            .unwrap();
+        });
+
+        assert_eq!(now.get(), 100);
+        assert_eq!(alarm.get(), keep_alive_alarm(100));
+    }
+
+    #[test]
+    pub fn sleep_until_handles_timestamps_after_the_clock_wraps_around() {
+        let timer = FakeTimer::default();
+        timer.now.set(u32::MAX - 4);
+        let now = timer.now.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            let deadline = timer_driver.now().unwrap() + Duration::from_ms(10);
+            unsafe { executor::block_on(timer_driver.sleep_until(deadline)) }.unwrap();
+        });
+
+        assert_eq!(now.get(), 5);
+    }
+
+    #[test]
+    pub fn alarms_can_be_set_at_a_clock_value() {
+        let timer = FakeTimer::default();
+        timer.now.set(7);
+        let alarm = timer.alarm.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut with_callback = drivers.timer.with_callback(|_, _| {});
+            let mut timer = with_callback.init().unwrap();
+            let start = timer.get_current_clock().unwrap();
+            timer.set_alarm_at(start + Duration::from_ms(1500)).unwrap();
+        });
+
+        assert_eq!(alarm.get(), Some(1507));
+    }
+
+    fn tick_with_late_processing(missed_tick_behavior: MissedTickBehavior) -> Vec<(isize, u32)> {
+        let timer = FakeTimer::default();
+        let now = timer.now.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            let mut interval = timer_driver
+                .interval(Duration::from_ms(10))
+                .unwrap()
+                .with_missed_tick_behavior(missed_tick_behavior);
+            unsafe {
+                executor::block_on(async {
+                    let mut ticks = Vec::new();
+                    for _ in 0..4 {
+                        let tick = interval.next().await.unwrap().unwrap();
+                        ticks.push((tick.ms(), now.get()));
+                        if ticks.len() == 1 {
+                            // Processing the first tick takes 25 ms.
+                            now.set(now.get() + 25);
+                        }
+                    }
+                    ticks
+                })
+            }
+        })
+    }
+
+    #[test]
+    pub fn interval_bursts_missed_ticks() {
+        assert_eq!(
+            tick_with_late_processing(MissedTickBehavior::Burst),
+            vec![(10, 10), (20, 35), (30, 35), (40, 40)]
+        );
+    }
+
+    #[test]
+    pub fn interval_delays_after_missed_ticks() {
+        assert_eq!(
+            tick_with_late_processing(MissedTickBehavior::Delay),
+            vec![(10, 10), (20, 35), (45, 45), (55, 55)]
+        );
+    }
+
+    #[test]
+    pub fn interval_skips_missed_ticks() {
+        assert_eq!(
+            tick_with_late_processing(MissedTickBehavior::Skip),
+            vec![(10, 10), (20, 35), (40, 40), (50, 50)]
+        );
+    }
+
+    #[test]
+    pub fn interval_accumulates_fractional_ticks() {
+        let timer = FakeTimer {
+            frequency: 32768,
+            ..FakeTimer::default()
+        };
+        let now = timer.now.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        let first_tick = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            // 327.68 ticks per period.
+            let mut interval = timer_driver.interval(Duration::from_ms(10)).unwrap();
+            unsafe {
+                executor::block_on(async {
+                    let first_tick = interval.tick().await.unwrap();
+                    for _ in 1..25 {
+                        interval.tick().await.unwrap();
+                    }
+                    first_tick
+                })
+            }
+        });
+
+        assert_eq!(first_tick.ms(), 9);
+        assert_eq!(now.get(), 8192);
+    }
+
+    #[test]
+    pub fn dropped_intervals_stop_their_alarm() {
+        let timer = FakeTimer::default();
+        let alarm = timer.alarm.clone();
+        let alarm_in_app = timer.alarm.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        let alarm_while_waiting = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            let mut interval = timer_driver.interval(Duration::from_ms(10)).unwrap();
+            unsafe {
+                executor::block_on(async {
+                    match futures::select(interval.tick(), ::futures::future::ready(())).await {
+                        Either::Right(()) => alarm_in_app.get(),
+                        Either::Left(_) => panic!("Expected the interval to be pending"),
+                    }
+                })
+            }
+        });
+
+        assert_eq!(alarm_while_waiting, Some(10));
+        assert_eq!(alarm.get(), keep_alive_alarm(0));
+    }
+
+    fn instants_at(frequency: u32, counter_width: u32, clock_values: &[u32]) -> Vec<Instant> {
+        let timer = FakeTimer {
+            frequency,
+            counter_width,
+            ..FakeTimer::default()
+        };
+        let now = timer.now.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        kernel.run(|| {
+            clock_values
+                .iter()
+                .map(|&clock_value| {
+                    now.set(clock_value);
+                    Instant::now().unwrap()
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    pub fn instants_survive_the_wrap_around_of_24_bit_counters() {
+        let ticks: Vec<u64> = instants_at(32768, 24, &[100, 0xff_fff0, 5, 0xff_fff0, 3])
+            .into_iter()
+            .map(Instant::ticks)
+            .collect();
+        assert_eq!(
+            ticks,
+            vec![100, 0xff_fff0, 0x100_0005, 0x1ff_fff0, 0x200_0003]
+        );
+    }
+
+    #[test]
+    pub fn instants_survive_the_wrap_around_of_32_bit_counters() {
+        let instants = instants_at(1000, 32, &[0x00ff_ffff, 0x8000_0000, 0xffff_fff0, 10]);
+        assert_eq!(instants[3].ticks(), 0x1_0000_000a);
+        assert_eq!(
+            instants[3] - instants[2],
+            core::time::Duration::from_millis(26)
+        );
+        assert!(instants[3] > instants[2]);
+    }
+
+    #[test]
+    pub fn counters_are_32_bit_wide_if_the_kernel_does_not_report_the_width() {
+        let instants = instants_at(1000, 0, &[0x00ff_fff0, 0x0100_0005, 0xffff_fff0, 10]);
+        let ticks: Vec<u64> = instants.into_iter().map(Instant::ticks).collect();
+        assert_eq!(
+            ticks,
+            vec![0x00ff_fff0, 0x0100_0005, 0xffff_fff0, 0x1_0000_000a]
+        );
+    }
+
+    #[test]
+    pub fn instants_stay_current_while_the_app_waits_for_other_events() {
+        const OTHER_DRIVER_NUMBER: usize = 0x99;
+
+        /// Delivers an upcall once the app has been idle a number of times.
+        struct SlowDriver {
+            idle_count: usize,
+        }
+
+        impl FakeDriver for SlowDriver {
+            fn command(
+                &mut self,
+                _: &mut DriverHandle,
+                _: usize,
+                _: usize,
+                _: usize,
+            ) -> CommandReturn {
+                CommandReturn::Success
+            }
+
+            fn idle(&mut self, kernel: &mut DriverHandle) {
+                self.idle_count += 1;
+                if self.idle_count == 8 {
+                    kernel.schedule_upcall(0, (0, 0, 0));
+                }
+            }
+        }
+
+        let timer = FakeTimer {
+            frequency: 32768,
+            counter_width: 24,
+            ..FakeTimer::default()
+        };
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+        kernel.add_driver(OTHER_DRIVER_NUMBER, SlowDriver { idle_count: 0 });
+
+        let (start, end) = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let _timer_driver = timer_driver.activate().unwrap();
+            let start = Instant::now().unwrap();
+            let upcall_delivered = Cell::new(false);
+            let mut callback = || upcall_delivered.set(true);
+            let subscription =
+                syscalls::subscribe::<Identity0Consumer, _>(OTHER_DRIVER_NUMBER, 0, &mut callback)
+                    .unwrap();
+            unsafe {
+                executor::block_on(futures::wait_until_on_upcall(
+                    OTHER_DRIVER_NUMBER,
+                    0,
+                    || upcall_delivered.get(),
+                ))
+            };
+            mem::drop(subscription);
+            (start, Instant::now().unwrap())
+        });
+
+        // Each time the app is idle, the alarm fires a quarter of the wrap-around period later.
+        assert_eq!(end.ticks() - start.ticks(), 8 << 22);
+    }
+
+    #[test]
+    pub fn instants_convert_to_durations() {
+        let instants = instants_at(32768, 32, &[16384, 3 * 32768 + 8192]);
+        assert_eq!(
+            instants[0].as_duration(),
+            core::time::Duration::from_millis(500)
+        );
+        assert_eq!(
+            instants[1].duration_since(instants[0]),
+            core::time::Duration::from_millis(2750)
+        );
+        assert_eq!(
+            instants[0].duration_since(instants[1]),
+            core::time::Duration::from_secs(0)
+        );
+    }
+
+    #[test]
+    pub fn large_clock_values_convert_to_positive_milliseconds() {
+        assert_eq!(ticks_to_ms(u32::MAX, 32768), 131_071_999);
+        assert_eq!(ticks_to_ms(1 << 31, 32768), 65_536_000);
+    }
+
+    #[test]
+    pub fn sleep_accepts_core_durations_with_microsecond_precision() {
+        let timer = FakeTimer {
+            frequency: 32768,
+            ..FakeTimer::default()
+        };
+        let now = timer.now.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            unsafe {
+                executor::block_on(timer_driver.sleep(core::time::Duration::from_micros(500)))
+            }
             .unwrap();
+        });
+
+        assert_eq!(now.get(), 16);
+    }
+
+    #[cfg(feature = "embedded-hal")]
+    #[test]
+    pub fn delays_block_until_the_alarm_has_fired() {
+        use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+
+        let timer = FakeTimer {
+            frequency: 32768,
+            ..FakeTimer::default()
+        };
+        let now = timer.now.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let mut timer_driver = timer_driver.activate().unwrap();
+            timer_driver.delay_ms(3u8);
+            timer_driver.delay_us(500u16);
+        });
+
+        assert_eq!(now.get(), 98 + 16);
+    }
+
+    #[test]
+    pub fn clock_values_convert_to_microseconds() {
+        let clock_value = ClockValue {
+            num_ticks: 3 * 32768 + 1,
+            clock_frequency: ClockFrequency { hz: 32768 },
+        };
+        assert_eq!(clock_value.ms(), 3000);
+        assert_eq!(clock_value.us(), 3_000_030);
+        assert_eq!(
+            clock_value.as_duration(),
+            core::time::Duration::new(3, 30_517)
+        );
+        assert_eq!(
+            (clock_value + core::time::Duration::from_micros(999_970)).num_ticks(),
+            4 * 32768
+        );
+    }
+
+    #[test]
+    pub fn large_clock_values_convert_to_positive_microseconds() {
+        // On 32-bit targets, clock values from bit 31 on are negative as `isize`.
+        let clock_value = ClockValue {
+            num_ticks: u32::MAX as i32 as isize,
+            clock_frequency: ClockFrequency { hz: 32768 },
+        };
+        assert_eq!(clock_value.us(), 131_071_999_969);
+        let clock_value = ClockValue {
+            num_ticks: (1u32 << 31) as i32 as isize,
+            clock_frequency: ClockFrequency { hz: 32768 },
+        };
+        assert_eq!(clock_value.us(), 65_536_000_000);
+    }
+
+    #[test]
+    pub fn legacy_durations_convert_to_core_durations() {
+        assert_eq!(
+            core::time::Duration::from(Duration::from_ms(1500usize)),
+            core::time::Duration::from_millis(1500)
+        );
+        assert_eq!(
+            core::time::Duration::from(Duration::from_ms(-5isize)),
+            core::time::Duration::from_millis(0)
+        );
+    }
+
+    /// Runs sleeps of the given lengths in concurrent tasks. A sleep is cancelled if a separate
+    /// task signals it after the optional cancellation time. Returns the completed and cancelled
+    /// sleeps with their time, and the remaining alarm.
+    /// The alarm which keeps the clock current while no sleep is pending, a quarter of the
+    /// wrap-around period of the fake timer after `now`.
+    fn keep_alive_alarm(now: u32) -> Option<u32> {
+        Some(now.wrapping_add(1 << 30))
+    }
+
+    fn run_sleeps(
+        sleeps: &[(&'static str, usize, Option<usize>)],
+    ) -> (Vec<(&'static str, u32)>, Option<u32>) {
+        let timer = FakeTimer::default();
+        let now = timer.now.clone();
+        let alarm = timer.alarm.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        let events = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = &timer_driver.activate().unwrap();
+            let events = &RefCell::new(Vec::new());
+            let now = &now;
+            let cancellations: Vec<Signal<()>> = sleeps.iter().map(|_| Signal::new()).collect();
+            let cancellations = &cancellations;
+
+            let sleep_tasks: Vec<_> = sleeps
+                .iter()
+                .enumerate()
+                .map(|(index, &(name, ms, _))| {
+                    Box::pin(Task::new(async move {
+                        let sleep = timer_driver.sleep(Duration::from_ms(ms));
+                        let event = match futures::select(sleep, cancellations[index].wait()).await
+                        {
+                            Either::Left(result) => {
+                                result.unwrap();
+                                name
+                            }
+                            Either::Right(()) => "cancelled",
+                        };
+                        events.borrow_mut().push((event, now.get()));
+                    }))
+                })
+                .collect();
+            let cancel_tasks: Vec<_> = sleeps
+                .iter()
+                .enumerate()
+                .filter_map(|(index, &(_, _, cancel_after))| {
+                    let cancel_after = cancel_after?;
+                    Some(Box::pin(Task::new(async move {
+                        timer_driver
+                            .sleep(Duration::from_ms(cancel_after))
+                            .await
+                            .unwrap();
+                        cancellations[index].signal(());
+                    })))
+                })
+                .collect();
+            let executor = Executor::new();
+            for task in &sleep_tasks {
+                executor.spawn(task.as_ref()).unwrap();
+            }
+            for task in &cancel_tasks {
+                executor.spawn(task.as_ref()).unwrap();
+            }
+            unsafe { executor::block_on(executor.run()) };
+
+            assert_eq!(timer_driver.context.active_timer.get(), None);
+            let events = events.borrow().clone();
+            events
+        });
+        (events, alarm.get())
+    }
+
+    #[test]
+    pub fn cancelling_the_active_sleep_activates_the_next_pending_one() {
+        // `b` suspends `a` and `c` waits behind `b`. Cancelling `b` has to activate `c`.
+        let (events, alarm) = run_sleeps(&[("a", 20, None), ("b", 10, Some(5)), ("c", 15, None)]);
+        assert_eq!(events, vec![("cancelled", 5), ("c", 15), ("a", 20)]);
+        assert_eq!(alarm, keep_alive_alarm(20));
+    }
+
+    #[test]
+    pub fn cancelled_suspended_sleeps_are_not_reactivated() {
+        // `b` suspends `a`, which is cancelled before `b` completes. `c` must not wait behind the
+        // cancelled `a`.
+        let (events, alarm) = run_sleeps(&[("a", 20, Some(5)), ("b", 10, None), ("c", 30, None)]);
+        assert_eq!(events, vec![("cancelled", 5), ("b", 10), ("c", 30)]);
+        assert_eq!(alarm, keep_alive_alarm(30));
+    }
+
+    #[test]
+    pub fn interleaved_cancellations_keep_the_earliest_deadline_active() {
+        let (events, alarm) = run_sleeps(&[
+            ("a", 40, Some(25)),
+            ("b", 10, Some(3)),
+            ("c", 30, None),
+            ("d", 12, Some(8)),
+            ("e", 20, None),
+        ]);
+        assert_eq!(
+            events,
+            vec![
+                ("cancelled", 3),
+                ("cancelled", 8),
+                ("e", 20),
+                ("cancelled", 25),
+                ("c", 30)
+            ]
+        );
+        assert_eq!(alarm, keep_alive_alarm(30));
+    }
+
+    #[test]
+    pub fn duration_bigger_than_frequency() {
+        let x = ParallelSleepDriver::compute_alarm_instant(
+            core::time::Duration::from_millis(10000),
+            0,
+            1000,
+        )
+        .ok()
+        .unwrap();
         assert_eq!(x, 10000);
     }
 
     #[test]
     pub fn frequency_bigger_than_duration() {
-        let x = ParallelSleepDriver::compute_alarm_instant(1000, 0, 10000)
-            .ok()
-            .unwrap();
+        let x = ParallelSleepDriver::compute_alarm_instant(
+            core::time::Duration::from_millis(1000),
+            0,
+            10000,
+        )
+        .ok()
+        .unwrap();
         assert_eq!(x, 10000);
     }
 
     #[test]
     pub fn fails_if_duration_is_too_large() {
-        let x =
-            ParallelSleepDriver::compute_alarm_instant(core::usize::MAX, 0, core::usize::MAX - 1);
+        let x = ParallelSleepDriver::compute_alarm_instant(
+            core::time::Duration::from_millis(core::usize::MAX as u64),
+            0,
+            core::usize::MAX - 1,
+        );
         assert!(x.is_err());
     }
 
     #[test]
     pub fn fails_if_frequency_is_too_large() {
-        let x =
-            ParallelSleepDriver::compute_alarm_instant(core::usize::MAX - 1, 0, core::usize::MAX);
+        let x = ParallelSleepDriver::compute_alarm_instant(
+            core::time::Duration::from_millis(core::usize::MAX as u64 - 1),
+            0,
+            core::usize::MAX,
+        );
         assert!(x.is_err());
     }
 
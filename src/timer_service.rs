@@ -0,0 +1,414 @@
+//! Software timers multiplexed onto the alarm of the timer driver. The caller provides the slots
+//! for the timers, so the number of timers which can run at the same time is the number of slots.
+//! Timers are either one-shot or periodic and invoke a callback when they expire. The callbacks
+//! are invoked by [TimerService::run], which has to be polled concurrently with the rest of the
+//! app.
+//!
+//! ```no_run
+//! # use core::cell::Cell;
+//! # use libtock::result::TockResult;
+//! # use libtock::timer::Duration;
+//! # use libtock::timer_service::TimerService;
+//! # use libtock::timer_service::TimerSlot;
+//! # async fn doc() -> TockResult<()> {
+//! # let mut drivers = libtock::retrieve_drivers()?;
+//! # let mut timer_driver = drivers.timer.create_timer_driver();
+//! # let timer_driver = timer_driver.activate()?;
+//! let retransmits = Cell::new(0);
+//! let retransmit = || retransmits.set(retransmits.get() + 1);
+//! let blink = || {};
+//! let mut slots = [TimerSlot::default(); 8];
+//! let timers = TimerService::new(&mut slots);
+//! let retransmit_timer = timers.start_once(Duration::from_ms(200), &retransmit)?;
+//! timers.start_periodic(Duration::from_ms(500), &blink)?;
+//! // On acknowledgement
+//! timers.cancel(retransmit_timer);
+//! timers.run(&timer_driver).await
+//! # }
+//! ```
+
+use crate::futures;
+use crate::futures::Either;
+use crate::result::OtherError;
+use crate::result::TockResult;
+use crate::sync::Signal;
+use crate::timer;
+use crate::timer::Instant;
+use crate::timer::ParallelSleepDriver;
+use core::cell::RefCell;
+use core::time::Duration;
+
+/// Invoked when a timer expires. Callbacks may start and cancel timers.
+pub type Callback<'a> = &'a dyn Fn();
+
+/// Storage for a timer of a [TimerService].
+#[derive(Copy, Clone, Default)]
+pub struct TimerSlot<'a> {
+    generation: usize,
+    timer: Option<SoftTimer<'a>>,
+}
+
+#[derive(Copy, Clone)]
+struct SoftTimer<'a> {
+    first_deadline: Instant,
+    period: Option<Duration>,
+    expirations: u64,
+    callback: Callback<'a>,
+}
+
+impl<'a> SoftTimer<'a> {
+    fn deadline(&self) -> Instant {
+        match self.period {
+            Some(period) => self.first_deadline + multiply(period, self.expirations),
+            None => self.first_deadline,
+        }
+    }
+
+    /// Skips the expirations of a periodic timer up to `now` at once, so long gaps, e.g. while
+    /// the app was busy, do not take a step per missed period.
+    fn skip_expirations(&mut self, now: Instant, period: Duration) {
+        let elapsed = (now - self.first_deadline).as_nanos();
+        self.expirations = (elapsed / period.as_nanos()) as u64 + 1;
+        // The deadline is rounded down to a tick, so it may not have passed `now` yet.
+        while self.deadline() <= now {
+            self.expirations += 1;
+        }
+    }
+}
+
+fn multiply(duration: Duration, factor: u64) -> Duration {
+    let nanos = duration.as_nanos() * u128::from(factor);
+    Duration::new(
+        (nanos / 1_000_000_000) as u64,
+        (nanos % 1_000_000_000) as u32,
+    )
+}
+
+/// Identifies a started timer. Handles of expired or cancelled timers stay invalid even if their
+/// slot is reused.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimerHandle {
+    index: usize,
+    generation: usize,
+}
+
+pub struct TimerService<'a> {
+    slots: RefCell<&'a mut [TimerSlot<'a>]>,
+    changed: Signal<()>,
+}
+
+impl<'a> TimerService<'a> {
+    pub fn new(slots: &'a mut [TimerSlot<'a>]) -> TimerService<'a> {
+        TimerService {
+            slots: RefCell::new(slots),
+            changed: Signal::new(),
+        }
+    }
+
+    /// Starts a timer which expires once after the delay.
+    pub fn start_once<D: Into<Duration>>(
+        &self,
+        delay: D,
+        callback: Callback<'a>,
+    ) -> TockResult<TimerHandle> {
+        self.start(delay.into(), None, callback)
+    }
+
+    /// Starts a timer which expires every period, starting one period from now. If expirations
+    /// have been missed, e.g. because a callback took too long, the callback is invoked only once
+    /// and the timer continues in phase with its original schedule.
+    pub fn start_periodic<D: Into<Duration>>(
+        &self,
+        period: D,
+        callback: Callback<'a>,
+    ) -> TockResult<TimerHandle> {
+        let period = period.into();
+        if period == Duration::from_secs(0) {
+            return Err(OtherError::TimerDriverDurationOutOfRange.into());
+        }
+        self.start(period, Some(period), callback)
+    }
+
+    fn start(
+        &self,
+        delay: Duration,
+        period: Option<Duration>,
+        callback: Callback<'a>,
+    ) -> TockResult<TimerHandle> {
+        let first_deadline = Instant::now()? + delay;
+        let mut slots = self.slots.borrow_mut();
+        let (index, slot) = slots
+            .iter_mut()
+            .enumerate()
+            .find(|(_, slot)| slot.timer.is_none())
+            .ok_or(OtherError::TimerServiceSlotsExhausted)?;
+        slot.generation = slot.generation.wrapping_add(1);
+        slot.timer = Some(SoftTimer {
+            first_deadline,
+            period,
+            expirations: 0,
+            callback,
+        });
+        self.changed.signal(());
+        Ok(TimerHandle {
+            index,
+            generation: slot.generation,
+        })
+    }
+
+    /// Stops the timer. Returns whether it was still active.
+    pub fn cancel(&self, handle: TimerHandle) -> bool {
+        let mut slots = self.slots.borrow_mut();
+        match slots.get_mut(handle.index) {
+            Some(slot) if slot.generation == handle.generation && slot.timer.is_some() => {
+                slot.timer = None;
+                self.changed.signal(());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns whether the timer has neither expired, if it is a one-shot timer, nor been
+    /// cancelled.
+    pub fn is_active(&self, handle: TimerHandle) -> bool {
+        match self.slots.borrow().get(handle.index) {
+            Some(slot) => slot.generation == handle.generation && slot.timer.is_some(),
+            None => false,
+        }
+    }
+
+    /// Invokes the callbacks of the expired timers and sleeps until the next timer expires or
+    /// timers are started or cancelled. Only returns on errors.
+    pub async fn run(&self, driver: &ParallelSleepDriver<'_>) -> TockResult<()> {
+        // Longer sleeps would not be measured correctly by the wrapping hardware counter.
+        let max_sleep = timer::wrap_around_period()? / 4;
+        loop {
+            self.expire(Instant::now()?);
+            match self.next_deadline() {
+                Some(deadline) => {
+                    let duration = deadline.duration_since(Instant::now()?).min(max_sleep);
+                    let sleep = driver.sleep(duration);
+                    if let Either::Left(result) = futures::select(sleep, self.changed.wait()).await
+                    {
+                        result?;
+                    }
+                }
+                None => self.changed.wait().await,
+            }
+        }
+    }
+
+    fn expire(&self, now: Instant) {
+        let num_slots = self.slots.borrow().len();
+        for index in 0..num_slots {
+            let callback = {
+                let mut slots = self.slots.borrow_mut();
+                let slot = &mut slots[index];
+                let mut timer = match slot.timer {
+                    Some(timer) if timer.deadline() <= now => timer,
+                    _ => continue,
+                };
+                match timer.period {
+                    Some(period) => {
+                        timer.skip_expirations(now, period);
+                        slot.timer = Some(timer);
+                    }
+                    None => slot.timer = None,
+                }
+                timer.callback
+            };
+            // The slots are not borrowed while the callback runs, so it can start and cancel
+            // timers.
+            callback();
+        }
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        self.slots
+            .borrow()
+            .iter()
+            .filter_map(|slot| slot.timer.as_ref().map(SoftTimer::deadline))
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::executor;
+    use crate::result::TockError;
+    use crate::syscalls::fake_kernel::FakeKernel;
+    use crate::timer::fake::FakeTimer;
+    use crate::timer::fake::DRIVER_NUMBER;
+    use core::cell::Cell;
+    use std::vec::Vec;
+
+    #[test]
+    pub fn one_shot_and_periodic_timers_expire_on_schedule() {
+        let timer = FakeTimer::default();
+        let now = timer.now.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        let expirations = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            let expirations = RefCell::new(Vec::new());
+            let on_periodic = || expirations.borrow_mut().push(("periodic", now.get()));
+            let on_once = || expirations.borrow_mut().push(("once", now.get()));
+            let on_cancelled = || expirations.borrow_mut().push(("cancelled", now.get()));
+            let mut slots = [TimerSlot::default(); 4];
+            let timers = TimerService::new(&mut slots);
+
+            let periodic = timers
+                .start_periodic(Duration::from_millis(10), &on_periodic)
+                .unwrap();
+            let once = timers
+                .start_once(Duration::from_millis(15), &on_once)
+                .unwrap();
+            let cancelled = timers
+                .start_once(Duration::from_millis(5), &on_cancelled)
+                .unwrap();
+            assert!(timers.cancel(cancelled));
+            unsafe {
+                executor::block_on(futures::select(
+                    timers.run(&timer_driver),
+                    timer_driver.sleep(Duration::from_millis(35)),
+                ))
+            };
+            assert!(timers.is_active(periodic));
+            assert!(!timers.is_active(once));
+            expirations.into_inner()
+        });
+
+        assert_eq!(
+            expirations,
+            vec![
+                ("periodic", 10),
+                ("once", 15),
+                ("periodic", 20),
+                ("periodic", 30)
+            ]
+        );
+    }
+
+    #[test]
+    pub fn timers_started_while_running_are_scheduled() {
+        let timer = FakeTimer::default();
+        let now = timer.now.clone();
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        let expired_at = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            let expired_at = Cell::new(None);
+            let expired = || expired_at.set(Some(now.get()));
+            let idle = || {};
+            let mut slots = [TimerSlot::default(); 2];
+            let timers = TimerService::new(&mut slots);
+            timers
+                .start_once(Duration::from_millis(100), &idle)
+                .unwrap();
+            unsafe {
+                executor::block_on(futures::select(timers.run(&timer_driver), async {
+                    timer_driver.sleep(Duration::from_millis(5)).await.unwrap();
+                    timers
+                        .start_once(Duration::from_millis(2), &expired)
+                        .unwrap();
+                    timer_driver.sleep(Duration::from_millis(10)).await.unwrap();
+                }))
+            };
+            expired_at.get()
+        });
+
+        assert_eq!(expired_at, Some(7));
+    }
+
+    #[test]
+    pub fn one_shot_timers_longer_than_a_wrap_around_expire_on_time() {
+        // The 32 bit counter wraps around every 64 s.
+        let timer = FakeTimer {
+            frequency: 1 << 26,
+            ..FakeTimer::default()
+        };
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, timer);
+
+        let elapsed = kernel.run(|| {
+            let mut drivers = unsafe { crate::drivers::retrieve_drivers_unsafe() };
+            let mut timer_driver = drivers.timer.create_timer_driver();
+            let timer_driver = timer_driver.activate().unwrap();
+            let start = Instant::now().unwrap();
+            let expired = Signal::new();
+            let on_expired = || expired.signal(());
+            let mut slots = [TimerSlot::default(); 1];
+            let timers = TimerService::new(&mut slots);
+            timers
+                .start_once(Duration::from_secs(150), &on_expired)
+                .unwrap();
+            unsafe {
+                executor::block_on(futures::select(timers.run(&timer_driver), expired.wait()))
+            };
+            start.elapsed().unwrap()
+        });
+
+        assert_eq!(elapsed, Duration::from_secs(150));
+    }
+
+    #[test]
+    pub fn periodic_timers_catch_up_on_long_gaps_at_once() {
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, FakeTimer::default());
+
+        kernel.run(|| {
+            let num_calls = Cell::new(0);
+            let callback = || num_calls.set(num_calls.get() + 1);
+            let mut slots = [TimerSlot::default(); 1];
+            let timers = TimerService::new(&mut slots);
+            let start = Instant::now().unwrap();
+            timers
+                .start_periodic(Duration::from_millis(1), &callback)
+                .unwrap();
+
+            // More expirations than fit into 32 bits.
+            let now = start + Duration::from_secs(50 * 24 * 60 * 60) + Duration::from_micros(500);
+            timers.expire(now);
+            timers.expire(now);
+
+            assert_eq!(num_calls.get(), 1);
+            assert_eq!(
+                timers.next_deadline(),
+                Some(start + Duration::from_secs(50 * 24 * 60 * 60) + Duration::from_millis(1))
+            );
+        });
+    }
+
+    #[test]
+    pub fn stale_handles_do_not_cancel_reused_slots() {
+        let mut kernel = FakeKernel::new();
+        kernel.add_driver(DRIVER_NUMBER, FakeTimer::default());
+
+        kernel.run(|| {
+            let callback = || {};
+            let mut slots = [TimerSlot::default(); 1];
+            let timers = TimerService::new(&mut slots);
+            let first = timers
+                .start_once(Duration::from_millis(1), &callback)
+                .unwrap();
+            assert!(timers.cancel(first));
+            let second = timers
+                .start_once(Duration::from_millis(1), &callback)
+                .unwrap();
+            assert!(!timers.cancel(first));
+            assert!(timers.is_active(second));
+            match timers.start_once(Duration::from_millis(1), &callback) {
+                Err(TockError::Other(OtherError::TimerServiceSlotsExhausted)) => {}
+                _ => panic!("Expected the slots to be exhausted"),
+            }
+        });
+    }
+}
@@ -0,0 +1,293 @@
+//! Decodes the records written by the `binlog` module of libtock.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// A log statement as interned in the symbol name `binlog;<level>;<crate>;<index>;<format>`.
+#[derive(Debug, PartialEq)]
+pub struct Statement {
+    pub level: String,
+    pub krate: String,
+    pub format: String,
+}
+
+impl Statement {
+    pub fn from_symbol(symbol: &str) -> Option<Statement> {
+        let mut parts = symbol.splitn(5, ';');
+        if parts.next() != Some("binlog") {
+            return None;
+        }
+        let level = parts.next()?.to_uppercase();
+        let krate = parts.next()?.to_string();
+        let _index = parts.next()?;
+        let format = parts.next()?.to_string();
+        Some(Statement {
+            level,
+            krate,
+            format,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Text(String),
+    Argument { ty: &'a str, hint: &'a str },
+}
+
+fn parse_format(format: &str) -> Result<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut rest = format;
+    while let Some(position) = rest.find(&['{', '}'][..]) {
+        text.push_str(&rest[..position]);
+        let tail = &rest[position..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            text.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        if tail.starts_with('}') {
+            return Err("unmatched `}`".into());
+        }
+        let end = tail.find('}').ok_or("unterminated placeholder")?;
+        let placeholder = &tail[1..end];
+        if !placeholder.starts_with('=') {
+            return Err(format!("invalid placeholder `{{{}}}`", placeholder));
+        }
+        let mut parts = placeholder[1..].splitn(2, ':');
+        let ty = parts.next().unwrap_or_default();
+        let hint = parts.next().unwrap_or_default();
+        if !text.is_empty() {
+            segments.push(Segment::Text(std::mem::take(&mut text)));
+        }
+        segments.push(Segment::Argument { ty, hint });
+        rest = &tail[end + 1..];
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+/// Decodes a COBS-encoded record without the terminating zero byte.
+pub fn cobs_decode(encoded: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut position = 0;
+    while position < encoded.len() {
+        let code = encoded[position] as usize;
+        if code == 0 || position + code > encoded.len() {
+            return Err("invalid COBS encoding".into());
+        }
+        decoded.extend_from_slice(&encoded[position + 1..position + code]);
+        position += code;
+        if code != 0xff && position < encoded.len() {
+            decoded.push(0);
+        }
+    }
+    Ok(decoded)
+}
+
+struct Frame<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        if len > self.data.len() {
+            return Err("record truncated".into());
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn fixed(&mut self, len: usize) -> Result<u64, String> {
+        let bytes = self.take(len)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, &byte| value << 8 | u64::from(byte)))
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("varint too long".into())
+    }
+}
+
+/// Maps the indices of the log statements to the statements.
+pub struct Decoder {
+    statements: HashMap<u64, Statement>,
+}
+
+impl Decoder {
+    pub fn new(symbols: HashMap<u64, String>) -> Decoder {
+        let statements = symbols
+            .into_iter()
+            .filter_map(|(index, symbol)| Some((index, Statement::from_symbol(&symbol)?)))
+            .collect();
+        Decoder { statements }
+    }
+
+    /// Decodes a record, given without COBS encoding, into a line like `INFO  [app] text`.
+    pub fn decode(&self, record: &[u8]) -> Result<String, String> {
+        let mut frame = Frame { data: record };
+        let index = frame.varint()?;
+        let statement = self
+            .statements
+            .get(&index)
+            .ok_or_else(|| format!("unknown log statement {}", index))?;
+        let mut line = format!("{:5} [{}] ", statement.level, statement.krate);
+        for segment in parse_format(&statement.format)? {
+            match segment {
+                Segment::Text(text) => line.push_str(&text),
+                Segment::Argument { ty, hint } => write_argument(&mut line, &mut frame, ty, hint)?,
+            }
+        }
+        if !frame.data.is_empty() {
+            return Err("record has trailing bytes".into());
+        }
+        Ok(line)
+    }
+}
+
+fn write_argument(
+    line: &mut String,
+    frame: &mut Frame,
+    ty: &str,
+    hint: &str,
+) -> Result<(), String> {
+    let (unsigned, signed) = match ty {
+        "u8" => (frame.fixed(1)?, None),
+        "u16" => (frame.fixed(2)?, None),
+        "u32" => (frame.fixed(4)?, None),
+        "u64" => (frame.fixed(8)?, None),
+        "usize" => (frame.varint()?, None),
+        "i8" => sign_extend(frame.fixed(1)?, 8),
+        "i16" => sign_extend(frame.fixed(2)?, 16),
+        "i32" => sign_extend(frame.fixed(4)?, 32),
+        "i64" => sign_extend(frame.fixed(8)?, 64),
+        "isize" => {
+            let zigzag = frame.varint()?;
+            let value = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+            (value as u64, Some(value))
+        }
+        "bool" => {
+            line.push_str(if frame.take(1)?[0] != 0 {
+                "true"
+            } else {
+                "false"
+            });
+            return Ok(());
+        }
+        "str" => {
+            let len = frame.varint()? as usize;
+            line.push_str(&String::from_utf8_lossy(frame.take(len)?));
+            return Ok(());
+        }
+        "[u8]" => {
+            let len = frame.varint()? as usize;
+            let bytes = frame.take(len)?;
+            let _ = match hint {
+                "x" => write!(line, "{:02x?}", bytes),
+                _ => write!(line, "{:?}", bytes),
+            };
+            return Ok(());
+        }
+        _ => return Err(format!("unknown argument type `{}`", ty)),
+    };
+    let _ = match (hint, signed) {
+        ("x", _) => write!(line, "{:#x}", unsigned),
+        ("b", _) => write!(line, "{:#b}", unsigned),
+        (_, Some(signed)) => write!(line, "{}", signed),
+        (_, None) => write!(line, "{}", unsigned),
+    };
+    Ok(())
+}
+
+/// Returns the value as unsigned integer of the given width and as signed integer.
+fn sign_extend(value: u64, bits: u32) -> (u64, Option<i64>) {
+    let shift = 64 - bits;
+    (value, Some(((value << shift) as i64) >> shift))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn decoder() -> Decoder {
+        let mut symbols = HashMap::new();
+        symbols.insert(0, "binlog;info;app;0;{=u8} sensors".to_string());
+        symbols.insert(
+            300,
+            "binlog;warn;app;1;{{{=i16}}} {=u32:x} {=isize} {=bool} {=str} {=[u8]}".to_string(),
+        );
+        Decoder::new(symbols)
+    }
+
+    #[test]
+    fn decodes_cobs() {
+        assert_eq!(
+            cobs_decode(&[0x02, 0x01, 0x02, 0x01, 0x01, 0x01]),
+            Ok(vec![0x01, 0x00, 0x01, 0x00, 0x00])
+        );
+        assert_eq!(cobs_decode(&[0x01]), Ok(vec![]));
+        let mut encoded = vec![0xff];
+        encoded.extend_from_slice(&[7; 254]);
+        encoded.extend_from_slice(&[0x02, 8]);
+        let mut decoded = vec![7; 254];
+        decoded.push(8);
+        assert_eq!(cobs_decode(&encoded), Ok(decoded));
+        assert!(cobs_decode(&[0x03, 0x01]).is_err());
+    }
+
+    #[test]
+    fn parses_format_strings() {
+        assert_eq!(
+            parse_format("a {{{=u8:x}}} b"),
+            Ok(vec![
+                Segment::Text("a {".into()),
+                Segment::Argument {
+                    ty: "u8",
+                    hint: "x"
+                },
+                Segment::Text("} b".into()),
+            ])
+        );
+        assert!(parse_format("{}").is_err());
+        assert!(parse_format("{=u8").is_err());
+    }
+
+    #[test]
+    fn decodes_records() {
+        let decoder = decoder();
+        assert_eq!(
+            decoder.decode(&[0x00, 3]),
+            Ok("INFO  [app] 3 sensors".into())
+        );
+        assert_eq!(
+            decoder.decode(&[
+                0xac, 0x02, 0xfe, 0xff, 0xef, 0xbe, 0x00, 0x00, 0x03, 0x01, 0x02, b'o', b'k', 0x02,
+                1, 2
+            ]),
+            Ok("WARN  [app] {-2} 0xbeef -2 true ok [1, 2]".into())
+        );
+    }
+
+    #[test]
+    fn reports_malformed_records() {
+        let decoder = decoder();
+        assert!(decoder.decode(&[0x05]).is_err());
+        assert!(decoder.decode(&[0x00]).is_err());
+        assert!(decoder.decode(&[0x00, 3, 4]).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! Minimal reader for little-endian ELF32 and ELF64 files which extracts the symbols of the
+//! `.binlog` section.
+
+use std::collections::HashMap;
+
+const SECTION_NAME: &str = ".binlog";
+const SHT_SYMTAB: u32 = 2;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    is_64_bit: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], String> {
+        offset
+            .checked_add(len)
+            .and_then(|end| self.data.get(offset..end))
+            .ok_or_else(|| format!("ELF file truncated at offset {:#x}", offset))
+    }
+
+    fn u16(&self, offset: usize) -> Result<u16, String> {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(self.bytes(offset, 2)?);
+        Ok(u16::from_le_bytes(bytes))
+    }
+
+    fn u32(&self, offset: usize) -> Result<u32, String> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.bytes(offset, 4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&self, offset: usize) -> Result<u64, String> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.bytes(offset, 8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    /// Reads an address or offset, which is 32 or 64 bits wide depending on the ELF class.
+    fn word(&self, offset: usize) -> Result<u64, String> {
+        if self.is_64_bit {
+            self.u64(offset)
+        } else {
+            self.u32(offset).map(u64::from)
+        }
+    }
+
+    fn string(&self, offset: usize) -> Result<&'a str, String> {
+        let bytes = self.data.get(offset..).unwrap_or_default();
+        let len = bytes
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or("unterminated string in ELF file")?;
+        std::str::from_utf8(&bytes[..len]).map_err(|_| "string in ELF file is not UTF-8".into())
+    }
+}
+
+struct Section {
+    name: u32,
+    kind: u32,
+    address: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    entry_size: u64,
+}
+
+/// Returns the names of the symbols in the `.binlog` section by their offset into the section,
+/// which is the index sent by the log statements.
+pub fn binlog_symbols(data: &[u8]) -> Result<HashMap<u64, String>, String> {
+    if data.len() < 6 || &data[..4] != b"\x7fELF" {
+        return Err("not an ELF file".into());
+    }
+    if data[5] != 1 {
+        return Err("only little-endian ELF files are supported".into());
+    }
+    let reader = Reader {
+        data,
+        is_64_bit: data[4] == 2,
+    };
+    let sections = read_sections(&reader)?;
+    let string_table = reader.u16(if reader.is_64_bit { 0x3e } else { 0x32 })? as usize;
+    let names = sections
+        .get(string_table)
+        .ok_or("invalid section name table")?;
+    let binlog_index = sections
+        .iter()
+        .position(|section| {
+            reader.string((names.offset + u64::from(section.name)) as usize) == Ok(SECTION_NAME)
+        })
+        .ok_or("ELF file has no .binlog section")?;
+    let binlog = &sections[binlog_index];
+
+    let mut symbols = HashMap::new();
+    for symbol_table in sections.iter().filter(|section| section.kind == SHT_SYMTAB) {
+        let strings = sections
+            .get(symbol_table.link as usize)
+            .ok_or("invalid symbol string table")?;
+        if symbol_table.entry_size == 0 {
+            return Err("invalid symbol table entry size".into());
+        }
+        for entry in 0..symbol_table.size / symbol_table.entry_size {
+            let offset = (symbol_table.offset + entry * symbol_table.entry_size) as usize;
+            let (name, value, section) = if reader.is_64_bit {
+                (
+                    reader.u32(offset)?,
+                    reader.u64(offset + 8)?,
+                    reader.u16(offset + 6)?,
+                )
+            } else {
+                (
+                    reader.u32(offset)?,
+                    u64::from(reader.u32(offset + 4)?),
+                    reader.u16(offset + 14)?,
+                )
+            };
+            if section as usize != binlog_index {
+                continue;
+            }
+            let name = reader.string((strings.offset + u64::from(name)) as usize)?;
+            if name.starts_with("binlog;") {
+                symbols.insert(value.wrapping_sub(binlog.address), name.to_string());
+            }
+        }
+    }
+    Ok(symbols)
+}
+
+fn read_sections(reader: &Reader) -> Result<Vec<Section>, String> {
+    let (table_offset, entry_size, count) = if reader.is_64_bit {
+        (reader.u64(0x28)?, reader.u16(0x3a)?, reader.u16(0x3c)?)
+    } else {
+        (
+            u64::from(reader.u32(0x20)?),
+            reader.u16(0x2e)?,
+            reader.u16(0x30)?,
+        )
+    };
+    (0..u64::from(count))
+        .map(|index| {
+            let offset = (table_offset + index * u64::from(entry_size)) as usize;
+            let word = if reader.is_64_bit { 8 } else { 4 };
+            Ok(Section {
+                name: reader.u32(offset)?,
+                kind: reader.u32(offset + 4)?,
+                address: reader.word(offset + 8 + word)?,
+                offset: reader.word(offset + 8 + 2 * word)?,
+                size: reader.word(offset + 8 + 3 * word)?,
+                link: reader.u32(offset + 8 + 4 * word)?,
+                entry_size: reader.word(offset + 16 + 5 * word)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use super::*;
+
+    #[used]
+    #[link_section = ".binlog"]
+    #[export_name = "binlog;info;binlog_decoder;0;answer: {=u8}"]
+    static SITE: u8 = 0;
+
+    #[test]
+    fn finds_the_binlog_symbols_of_an_executable() {
+        let data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let symbols = binlog_symbols(&data).unwrap();
+        assert!(symbols
+            .values()
+            .any(|name| name == "binlog;info;binlog_decoder;0;answer: {=u8}"));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(binlog_symbols(b"#!/bin/sh").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+//! Decodes the output of the `binlog` module of libtock.
+//!
+//! Usage: `binlog-decoder <elf> [input]`
+//!
+//! `<elf>` is the ELF file of the app producing the output. The output is read from `[input]`, or
+//! from standard input if omitted, e.g. `cat /dev/ttyACM0 | binlog-decoder app.elf`.
+
+mod decoder;
+mod elf;
+
+use decoder::cobs_decode;
+use decoder::Decoder;
+use std::env;
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("Usage: {} <elf> [input]", args[0]);
+        process::exit(2);
+    }
+    if let Err(message) = run(&args[1], args.get(2)) {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}
+
+fn run(elf_path: &str, input_path: Option<&String>) -> Result<(), String> {
+    let elf = std::fs::read(elf_path).map_err(|err| format!("{}: {}", elf_path, err))?;
+    let decoder = Decoder::new(elf::binlog_symbols(&elf)?);
+    let input: Box<dyn BufRead> = match input_path {
+        Some(path) => Box::new(BufReader::new(
+            File::open(path).map_err(|err| format!("{}: {}", path, err))?,
+        )),
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+    for encoded in input.split(0) {
+        let encoded = encoded.map_err(|err| err.to_string())?;
+        if encoded.is_empty() {
+            continue;
+        }
+        match cobs_decode(&encoded).and_then(|record| decoder.decode(&record)) {
+            Ok(line) => println!("{}", line),
+            Err(message) => eprintln!("malformed record ({}): {:02x?}", message, encoded),
+        }
+    }
+    Ok(())
+}